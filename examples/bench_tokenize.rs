@@ -0,0 +1,46 @@
+#![allow(clippy::needless_return)]
+
+// Measures the throughput of the tokenizer on a generated multi-megabyte
+// source, or on the given file.
+//
+// Run with: cargo run --release --example bench_tokenize [FILE]
+
+extern crate solang;
+
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+use solang::read::span::Position;
+use solang::read::token::TokenReader;
+
+const RUNS: usize = 10;
+
+fn main() {
+    let source = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(&path).expect("failed to read input file"),
+        None => generate(8 * 1024 * 1024)
+    };
+
+    // Best of a few runs, to leave out warm-up and scheduling noise.
+    let mut best = f64::MAX;
+    let mut count = 0;
+    for _ in 0 .. RUNS {
+        let t0 = Instant::now();
+        count = TokenReader::new(&source, Position::start()).count();
+        best = best.min(t0.elapsed().as_secs_f64());
+    }
+
+    println!("tokenized {} bytes into {} tokens in {:.3}s ({:.1} MB/s, best of {})",
+             source.len(), count, best, source.len() as f64 / best / 1e6, RUNS);
+}
+
+// Mixes the kinds of tokens found in generated data files, with some
+// non-ASCII text.
+fn generate(size: usize) -> String {
+    let chunk = "(def 北京 {:population 21_540_000 :area 16410.54 :tags #{\"capital\" \"市\"}})\n\
+                 ; generated data\n\
+                 [0xFF -42 nil true 'sym `(,x ,@ys) \\a \"a \\\"quoted\\\" string\"]\n";
+
+    return chunk.repeat(size / chunk.len());
+}
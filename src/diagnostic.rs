@@ -0,0 +1,517 @@
+use std::sync::Arc;
+
+use ::read::span::Span;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+// Named source text that spans point into, e.g. a module file or the
+// input typed into the REPL so far.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Source {
+    pub name:   String,
+    pub text:   Arc<String>
+}
+
+// A span with a short message shown under it.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Label {
+    pub span:       Span,
+    pub message:    String
+}
+
+// A read or eval problem with a stable code, e.g. R0007, that is rendered
+// with the source lines its labels point to.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Diagnostic {
+    pub code:       &'static str,
+    pub severity:   Severity,
+    pub message:    String,
+    pub primary:    Option<Label>,
+    pub secondary:  Vec<Label>,
+    pub notes:      Vec<String>,
+    pub help:       Option<String>,
+    pub source:     Option<Source>,
+    pub children:   Vec<Diagnostic>
+}
+
+impl Source {
+    pub fn new(name: &str, text: &str) -> Source {
+        return Source {
+            name:   name.to_string(),
+            text:   Arc::new(text.to_string())
+        }
+    }
+
+    // Appends text, copying it only when a diagnostic still shares it.
+    pub fn push_str(&mut self, text: &str) {
+        Arc::make_mut(&mut self.text).push_str(text);
+    }
+}
+
+impl Label {
+    pub fn new(span: Span, message: &str) -> Label {
+        return Label {
+            span,
+            message: message.to_string()
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, severity: Severity, message: &str) -> Diagnostic {
+        return Diagnostic {
+            code,
+            severity,
+            message:    message.to_string(),
+            primary:    None,
+            secondary:  Vec::new(),
+            notes:      Vec::new(),
+            help:       None,
+            source:     None,
+            children:   Vec::new()
+        }
+    }
+
+    pub fn error(code: &'static str, message: &str) -> Diagnostic {
+        return Diagnostic::new(code, Severity::Error, message);
+    }
+
+    pub fn warning(code: &'static str, message: &str) -> Diagnostic {
+        return Diagnostic::new(code, Severity::Warning, message);
+    }
+
+    pub fn with_primary(mut self, span: Span, message: &str) -> Diagnostic {
+        self.primary = Some(Label::new(span, message));
+        return self;
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: &str) -> Diagnostic {
+        self.secondary.push(Label::new(span, message));
+        return self;
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        return self;
+    }
+
+    pub fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help = Some(help.to_string());
+        return self;
+    }
+
+    pub fn with_source(mut self, source: &Source) -> Diagnostic {
+        self.source = Some(source.clone());
+        return self;
+    }
+
+    pub fn with_children(mut self, children: Vec<Diagnostic>) -> Diagnostic {
+        self.children = children;
+        return self;
+    }
+
+    // Renders the diagnostic and its children, showing each labelled line
+    // of its own source, or else of the given one:
+    //
+    //   error[R0007]: invalid list close delimiter: ']'
+    //    --> <repl>:1:5
+    //     |
+    //   1 | (foo]
+    //     | - list opened here
+    //     |     ^ expected ')'
+    pub fn render(&self, source: Option<&Source>) -> String {
+        let source = match self.source {
+            Some(ref own_source) => Some(own_source),
+            None => source
+        };
+
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        };
+
+        let mut labels = self.primary
+            .iter()
+            .map(|label| (label, '^'))
+            .chain(self.secondary.iter().map(|label| (label, '-')))
+            .collect::<Vec<_>>();
+        labels.sort_by_key(|(label, _)| label.span.start.offset);
+
+        let gutter_size = labels
+            .iter()
+            .map(|(label, _)| label.span.start.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(gutter_size);
+
+        let mut s = format!("{}[{}]: {}\n", severity, self.code, self.message);
+
+        match (&self.primary, source) {
+            (Some(primary), Some(source)) => {
+                s += &format!("{}--> {}:{}\n", gutter, source.name, primary.span.start);
+            },
+
+            (Some(primary), None) => {
+                s += &format!("{}--> {}\n", gutter, primary.span.start);
+            },
+
+            (None, Some(source)) => {
+                s += &format!(" --> {}\n", source.name);
+            },
+
+            (None, None) => ()
+        }
+
+        let mut last_line = None;
+        for (label, mark) in labels.iter() {
+            let line = match source.and_then(|source| source_line(&source.text, label.span)) {
+                Some(line) => line,
+                None => continue
+            };
+
+            if last_line.is_none() {
+                s += &format!("{} |\n", gutter);
+            }
+
+            if last_line != Some(label.span.start.line) {
+                s += &format!("{:>width$} | {}\n", label.span.start.line, line.text, width = gutter_size);
+                last_line = Some(label.span.start.line);
+            }
+
+            // Tabs are kept so that the underline lines up with the text above.
+            let indent = line.text[.. line.start]
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect::<String>();
+            let width = line.text[line.start .. line.end].chars().count().max(1);
+            let underline = mark.to_string().repeat(width);
+
+            let row = format!("{}{} {}", indent, underline, label.message);
+            s += &format!("{} | {}\n", gutter, row.trim_end());
+        }
+
+        for note in self.notes.iter() {
+            s += &format!("{} = note: {}\n", gutter, note);
+        }
+
+        match self.help {
+            Some(ref help) => s += &format!("{} = help: {}\n", gutter, help),
+            None => ()
+        }
+
+        for child in self.children.iter() {
+            s += &child.render(source);
+        }
+
+        return s;
+    }
+}
+
+// The line a span starts on, with the byte range of the span within it,
+// cut at the end of the line for spans over several lines.
+struct SourceLine<'a> {
+    text:   &'a str,
+    start:  usize,
+    end:    usize
+}
+
+fn source_line<'a>(text: &'a str, span: Span) -> Option<SourceLine<'a>> {
+    if !text.is_char_boundary(span.start.offset) || !text.is_char_boundary(span.end.offset) {
+        return None;
+    }
+
+    let line_start = match text[.. span.start.offset].rfind('\n') {
+        Some(newline_offset) => newline_offset + 1,
+        None => 0
+    };
+
+    let line_end = match text[span.start.offset ..].find('\n') {
+        Some(newline_offset) => span.start.offset + newline_offset,
+        None => text.len()
+    };
+
+    let line = text[line_start .. line_end].trim_end_matches('\r');
+    let start = (span.start.offset - line_start).min(line.len());
+    let end = (span.end.offset.max(span.start.offset) - line_start).min(line.len());
+
+    return Some(SourceLine {
+        text: line,
+        start,
+        end
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ::eval::env::Env;
+    use ::eval::eval::eval;
+    use ::read::dispatch::DispatchTable;
+    use ::read::read_forms;
+    use ::read::span::Position;
+
+    fn span(source: &str, start: usize, end: usize) -> Span {
+        let start_position = Position::start().advance(&source[.. start]);
+        let end_position = start_position.advance(&source[start .. end]);
+        return Span::new(start_position, end_position);
+    }
+
+    #[test]
+    fn test_render_primary() {
+        let text = "(def x 1)\n(foo x)\n";
+        let source = Source::new("main.sol", text);
+        let diagnostic = Diagnostic::error("E0001", "undefined symbol: foo")
+            .with_primary(span(text, 11, 14), "not defined")
+            .with_help("define it with def before using it");
+
+        let exp = [
+            "error[E0001]: undefined symbol: foo",
+            " --> main.sol:2:2",
+            "  |",
+            "2 | (foo x)",
+            "  |  ^^^ not defined",
+            "  = help: define it with def before using it",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, diagnostic.render(Some(&source)));
+    }
+
+    #[test]
+    fn test_render_secondary() {
+        let text = "\n\n\n\n\n\n\n\n\n(foo\n\tbar]";
+        let source = Source::new("<repl>", text);
+        let diagnostic = Diagnostic::error("R0007", "invalid list close delimiter: ']'")
+            .with_primary(span(text, 18, 19), "expected ')'")
+            .with_secondary(span(text, 9, 10), "list opened here")
+            .with_note("the list is discarded");
+
+        let exp = [
+            "error[R0007]: invalid list close delimiter: ']'",
+            "  --> <repl>:11:5",
+            "   |",
+            "10 | (foo",
+            "   | - list opened here",
+            "11 | \tbar]",
+            "   | \t   ^ expected ')'",
+            "   = note: the list is discarded",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, diagnostic.render(Some(&source)));
+    }
+
+    #[test]
+    fn test_render_multi_line_span() {
+        let text = "(foo 北京\n  bar)";
+        let source = Source::new("<stdin>", text);
+        let diagnostic = Diagnostic::error("E0006", "invalid binding form in function, got 北京")
+            .with_primary(span(text, 5, text.len()), "");
+
+        let exp = [
+            "error[E0006]: invalid binding form in function, got 北京",
+            " --> <stdin>:1:6",
+            "  |",
+            "1 | (foo 北京",
+            "  |      ^^",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, diagnostic.render(Some(&source)));
+    }
+
+    #[test]
+    fn test_render_warning() {
+        let text = "(fn (x) 1)";
+        let source = Source::new("<repl>", text);
+        let diagnostic = Diagnostic::warning("W0003", "unused binding x")
+            .with_primary(span(text, 5, 6), "never used");
+
+        let exp = [
+            "warning[W0003]: unused binding x",
+            " --> <repl>:1:6",
+            "  |",
+            "1 | (fn (x) 1)",
+            "  |      ^ never used",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, diagnostic.render(Some(&source)));
+    }
+
+    #[test]
+    fn test_render_children() {
+        let text = "(foo\n";
+        let source = Source::new("foo.sol", text);
+        let child = Diagnostic::error("R0009", "non-terminated list")
+            .with_primary(span(text, 0, 1), "")
+            .with_source(&source);
+        let diagnostic = Diagnostic::error("E0021", "failed to read module foo")
+            .with_children(vec![child]);
+
+        let exp = [
+            "error[E0021]: failed to read module foo",
+            "error[R0009]: non-terminated list",
+            " --> foo.sol:1:1",
+            "  |",
+            "1 | (foo",
+            "  | ^",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, diagnostic.render(None));
+    }
+
+    #[test]
+    fn test_render_without_source() {
+        let text = "(foo";
+        let diagnostic = Diagnostic::error("R0009", "non-terminated list")
+            .with_primary(span(text, 0, 1), "unclosed");
+
+        assert_eq!("error[R0009]: non-terminated list\n --> 1:1\n", diagnostic.render(None));
+    }
+
+    #[test]
+    fn test_read_error() {
+        let text = "{a 1\n a 2}";
+        let source = Source::new("<repl>", text);
+        let read_errors = read_forms(text, &DispatchTable::new()).unwrap_err();
+
+        let exp = [
+            "error[R0011]: duplicate map key: a",
+            " --> <repl>:2:2",
+            "  |",
+            "1 | {a 1",
+            "  |  - first used here",
+            "2 |  a 2}",
+            "  |  ^ repeated here",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, read_errors[0].diagnostic().render(Some(&source)));
+    }
+
+    #[test]
+    fn test_eval_error() {
+        let text = "(def x 1) (+ x \"a\")";
+        let source = Source::new("<repl>", text);
+        let forms = read_forms(text, &DispatchTable::new()).unwrap();
+        let mut env = Env::new(&vec![], &sx_symbol_unwrapped!("core"));
+        assert!(eval(&mut env, &forms[0].sx).is_ok());
+        let eval_error = eval(&mut env, &forms[1].sx).unwrap_err();
+
+        let exp = [
+            "error[E0010]: argument 2 to + must be a number, got string \"a\"",
+            " --> <repl>:1:16",
+            "  |",
+            "1 | (def x 1) (+ x \"a\")",
+            "  |                ^^^ expected a number",
+            "  = note: signature: (+ & xs)",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, eval_error.diagnostic(Some(&forms[1])).render(Some(&source)));
+    }
+
+    #[test]
+    fn test_eval_error_argument() {
+        let text = "(def x \"a\") (do \"a\" (+ 1 x))";
+        let source = Source::new("<repl>", text);
+        let forms = read_forms(text, &DispatchTable::new()).unwrap();
+        let mut env = Env::new(&vec![], &sx_symbol_unwrapped!("core"));
+        assert!(eval(&mut env, &forms[0].sx).is_ok());
+        let eval_error = eval(&mut env, &forms[1].sx).unwrap_err();
+
+        let exp = [
+            "error[E0010]: argument 2 to + must be a number, got string \"a\"",
+            " --> <repl>:1:26",
+            "  |",
+            "1 | (def x \"a\") (do \"a\" (+ 1 x))",
+            "  |                          ^ expected a number",
+            "  = note: signature: (+ & xs)",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, eval_error.diagnostic(Some(&forms[1])).render(Some(&source)));
+    }
+
+    #[test]
+    fn test_eval_warning() {
+        let text = "(let ((x 1) (head 2)) head)";
+        let source = Source::new("<repl>", text);
+        let forms = read_forms(text, &DispatchTable::new()).unwrap();
+        let mut env = Env::new(&vec![], &sx_symbol_unwrapped!("user"));
+        assert!(eval(&mut env, &forms[0].sx).is_ok());
+
+        let exp = [
+            "warning[W0003]: unused binding x",
+            " --> <repl>:1:8",
+            "  |",
+            "1 | (let ((x 1) (head 2)) head)",
+            "  |        ^ never used",
+            "  = help: prefix it with an underscore if this is intended: _x",
+            "",
+            "warning[W0001]: binding head shadows the definition of head in module core",
+            " --> <repl>:1:14",
+            "  |",
+            "1 | (let ((x 1) (head 2)) head)",
+            "  |              ^^^^ shadows a definition",
+            "  = help: rename the binding",
+            ""
+        ].join("\n");
+
+        let act = env.take_warnings()
+            .iter()
+            .map(|warning| warning.diagnostic(Some(&forms[0])).render(Some(&source)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn test_eval_error_unpaired_key() {
+        let text = "(assoc {} 'a 1 'b)";
+        let source = Source::new("<repl>", text);
+        let forms = read_forms(text, &DispatchTable::new()).unwrap();
+        let mut env = Env::new(&vec![], &sx_symbol_unwrapped!("core"));
+        let eval_error = eval(&mut env, &forms[0].sx).unwrap_err();
+
+        let exp = [
+            "error[E0026]: assoc expects key/value pairs, got key b without a value",
+            " --> <repl>:1:17",
+            "  |",
+            "1 | (assoc {} 'a 1 'b)",
+            "  |                 ^ missing a value",
+            "  = note: signature: (assoc map key value & kvs)",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, eval_error.diagnostic(Some(&forms[0])).render(Some(&source)));
+    }
+
+    #[test]
+    fn test_eval_error_recursive_binding() {
+        let text = "(letrec ((f (fn () 1)) (y (f))) y)";
+        let source = Source::new("<repl>", text);
+        let forms = read_forms(text, &DispatchTable::new()).unwrap();
+        let mut env = Env::new(&vec![], &sx_symbol_unwrapped!("core"));
+        let eval_error = eval(&mut env, &forms[0].sx).unwrap_err();
+
+        let exp = [
+            "error[E0027]: letrec only binds functions, got (f) for y",
+            " --> <repl>:1:27",
+            "  |",
+            "1 | (letrec ((f (fn () 1)) (y (f))) y)",
+            "  |                           ^^^ not a fn form",
+            "  = help: bind it with let or let* instead",
+            ""
+        ].join("\n");
+
+        assert_eq!(exp, eval_error.diagnostic(Some(&forms[0])).render(Some(&source)));
+    }
+}
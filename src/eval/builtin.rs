@@ -1,11 +1,18 @@
+use std::mem;
+use std::result;
+use std::slice;
 use std::sync::Arc;
 
 use im;
+use num_bigint::{BigInt, Sign};
+use num_traits::ToPrimitive;
 use time;
+use unicode_segmentation::UnicodeSegmentation;
 
 use ::eval::env::Env;
-use ::eval::{module, Result, Error};
-use ::eval::eval::{eval, apply_builtin, apply_function};
+use ::eval::{module, Result, TailResult, Tail, Error, Warning};
+use ::eval::eval::{eval, eval_scoped, apply_builtin, apply_function, expand_macro};
+use ::eval::scope::Scope;
 use ::sx::{*};
 use ::util::pretty::pretty;
 
@@ -13,22 +20,63 @@ pub static BUILTIN_MODULE_NAME: &'static str = "core";
 
 pub static BUILTIN_TABLE: &'static [&SxBuiltinInfo] = &[
     // Specials
+    &SPECIAL_AND,
+    &SPECIAL_COND,
     &SPECIAL_DEF,
+    &SPECIAL_DEFMACRO,
+    &SPECIAL_DEFREADER,
+    &SPECIAL_DO,
     &SPECIAL_FN,
     &SPECIAL_IF,
+    &SPECIAL_LET,
+    &SPECIAL_LET_STAR,
+    &SPECIAL_LETREC,
     &SPECIAL_MODULE,
+    &SPECIAL_OR,
+    &SPECIAL_QUASIQUOTE,
     &SPECIAL_QUOTE,
+    &SPECIAL_UNLESS,
+    &SPECIAL_UNQUOTE,
+    &SPECIAL_UNQUOTE_SPLICING,
     &SPECIAL_USE,
+    &SPECIAL_WHEN,
 
     // General
     &PRIMITIVE_APPLY,
+    &PRIMITIVE_DOC,
     &PRIMITIVE_ENV,
+    &PRIMITIVE_GENSYM,
+    &PRIMITIVE_MACROEXPAND,
+    &PRIMITIVE_MACROEXPAND_1,
     &PRIMITIVE_TRACE,
 
+    // Tagged literals
+    &PRIMITIVE_TAGGED,
+    &PRIMITIVE_TAG,
+    &PRIMITIVE_UNTAG,
+
     // Collections
     &PRIMITIVE_CONS,
     &PRIMITIVE_HEAD,
     &PRIMITIVE_TAIL,
+    &PRIMITIVE_GET,
+    &PRIMITIVE_ASSOC,
+    &PRIMITIVE_DISSOC,
+    &PRIMITIVE_KEYS,
+    &PRIMITIVE_VALS,
+    &PRIMITIVE_CONTAINS,
+    &PRIMITIVE_CONJ,
+    &PRIMITIVE_DISJ,
+    &PRIMITIVE_UNION,
+    &PRIMITIVE_INTERSECTION,
+    &PRIMITIVE_DIFFERENCE,
+    &PRIMITIVE_SUBSET,
+
+    // Strings
+    &PRIMITIVE_CHAR_TO_INT,
+    &PRIMITIVE_INT_TO_CHAR,
+    &PRIMITIVE_STRING_TO_CHARS,
+    &PRIMITIVE_CHARS_TO_STRING,
 
     // Logic
     &PRIMITIVE_EQ,
@@ -40,17 +88,94 @@ pub static BUILTIN_TABLE: &'static [&SxBuiltinInfo] = &[
     &PRIMITIVE_RANGE
 ];
 
+pub fn lookup(name: &str) -> Option<&'static SxBuiltinInfo> {
+    return BUILTIN_TABLE.iter().find(|builtin| builtin.name == name).cloned();
+}
+
+// The error for an argument that is not of the type of its parameter.
+fn bad_arg(builtin: &SxBuiltinInfo, index: usize, arg: &Sx) -> Error {
+    let ty = match builtin.param(index) {
+        Some(param) => param.ty,
+        None => SxType::Any
+    };
+
+    return Error::BuiltinBadArg(builtin.name, index, ty, arg.clone());
+}
+
+static SPECIAL_AND: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "and",
+    min_arity:  0,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_and)
+};
+
+static SPECIAL_COND: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "cond",
+    min_arity:  0,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "clauses", ty: SxType::NonEmptyList }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_cond)
+};
+
 static SPECIAL_DEF: SxBuiltinInfo = SxBuiltinInfo {
     name:       "def",
     min_arity:  2,
     max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "name", ty: SxType::Symbol },
+        SxParam { name: "value", ty: SxType::Any }
+    ],
     callback:   SxBuiltinCallback::Special(special_def)
 };
 
+static SPECIAL_DEFMACRO: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "defmacro",
+    min_arity:  3,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "name", ty: SxType::Symbol },
+        SxParam { name: "bindings", ty: SxType::List },
+        SxParam { name: "expr", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Special(special_defmacro)
+};
+
+static SPECIAL_DEFREADER: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "defreader",
+    min_arity:  2,
+    max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "tag", ty: SxType::Symbol },
+        SxParam { name: "reader", ty: SxType::Function }
+    ],
+    callback:   SxBuiltinCallback::Special(special_defreader)
+};
+
+static SPECIAL_DO: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "do",
+    min_arity:  0,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_do)
+};
+
 static SPECIAL_FN: SxBuiltinInfo = SxBuiltinInfo {
     name:       "fn",
     min_arity:  2,
     max_arity:  None,
+    params:     &[
+        SxParam { name: "bindings", ty: SxType::List },
+        SxParam { name: "expr", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
     callback:   SxBuiltinCallback::Special(special_fn)
 };
 
@@ -58,27 +183,139 @@ static SPECIAL_IF: SxBuiltinInfo = SxBuiltinInfo {
     name:       "if",
     min_arity:  3,
     max_arity:  Some(3),
-    callback:   SxBuiltinCallback::Special(special_if)
+    params:     &[
+        SxParam { name: "cond", ty: SxType::Any },
+        SxParam { name: "then", ty: SxType::Any },
+        SxParam { name: "else", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_if)
+};
+
+static SPECIAL_LET: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "let",
+    min_arity:  2,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "bindings", ty: SxType::List },
+        SxParam { name: "expr", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_let)
+};
+
+static SPECIAL_LET_STAR: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "let*",
+    min_arity:  2,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "bindings", ty: SxType::List },
+        SxParam { name: "expr", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_let_star)
+};
+
+static SPECIAL_LETREC: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "letrec",
+    min_arity:  2,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "bindings", ty: SxType::List },
+        SxParam { name: "expr", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_letrec)
 };
 
 static SPECIAL_MODULE: SxBuiltinInfo = SxBuiltinInfo {
     name:       "module",
     min_arity:  1,
     max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "name", ty: SxType::Symbol }
+    ],
     callback:   SxBuiltinCallback::Special(special_module)
 };
 
+static SPECIAL_OR: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "or",
+    min_arity:  0,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_or)
+};
+
+static SPECIAL_QUASIQUOTE: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "quasiquote",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "template", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Special(special_quasiquote)
+};
+
 static SPECIAL_QUOTE: SxBuiltinInfo = SxBuiltinInfo {
     name:       "quote",
     min_arity:  1,
     max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "form", ty: SxType::Any }
+    ],
     callback:   SxBuiltinCallback::Special(special_quote)
 };
 
+static SPECIAL_UNLESS: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "unless",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "cond", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_unless)
+};
+
+static SPECIAL_UNQUOTE: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "unquote",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "form", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Special(special_unquote)
+};
+
+static SPECIAL_UNQUOTE_SPLICING: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "unquote-splicing",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "form", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Special(special_unquote_splicing)
+};
+
+static SPECIAL_WHEN: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "when",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "cond", ty: SxType::Any },
+        SxParam { name: "exprs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::TailSpecial(special_when)
+};
+
 static SPECIAL_USE: SxBuiltinInfo = SxBuiltinInfo {
     name:       "use",
     min_arity:  1,
     max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "module", ty: SxType::Symbol }
+    ],
     callback:   SxBuiltinCallback::Special(special_use)
 };
 
@@ -86,27 +323,111 @@ static PRIMITIVE_APPLY: SxBuiltinInfo = SxBuiltinInfo {
     name:       "apply",
     min_arity:  2,
     max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "f", ty: SxType::Function },
+        SxParam { name: "args", ty: SxType::List }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_apply)
 };
 
+static PRIMITIVE_DOC: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "doc",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "f", ty: SxType::Function }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_doc)
+};
+
 static PRIMITIVE_ENV: SxBuiltinInfo = SxBuiltinInfo {
     name:       "env",
     min_arity:  0,
     max_arity:  Some(0),
+    params:     &[],
     callback:   SxBuiltinCallback::Primitive(primitive_env)
 };
 
+static PRIMITIVE_GENSYM: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "gensym",
+    min_arity:  0,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "prefix", ty: SxType::String }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_gensym)
+};
+
+static PRIMITIVE_MACROEXPAND: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "macroexpand",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "form", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_macroexpand)
+};
+
+static PRIMITIVE_MACROEXPAND_1: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "macroexpand-1",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "form", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_macroexpand_1)
+};
+
 static PRIMITIVE_TRACE: SxBuiltinInfo = SxBuiltinInfo {
     name:       "trace",
     min_arity:  2,
     max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "label", ty: SxType::String },
+        SxParam { name: "value", ty: SxType::Any }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_trace)
 };
 
+static PRIMITIVE_TAGGED: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "tagged",
+    min_arity:  2,
+    max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "tag", ty: SxType::Symbol },
+        SxParam { name: "value", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_tagged)
+};
+
+static PRIMITIVE_TAG: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "tag",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "tagged", ty: SxType::Tagged }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_tag)
+};
+
+static PRIMITIVE_UNTAG: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "untag",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "tagged", ty: SxType::Tagged }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_untag)
+};
+
 static PRIMITIVE_CONS: SxBuiltinInfo = SxBuiltinInfo {
     name:       "cons",
     min_arity:  2,
     max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "x", ty: SxType::Any },
+        SxParam { name: "list", ty: SxType::List }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_cons)
 };
 
@@ -114,6 +435,9 @@ static PRIMITIVE_HEAD: SxBuiltinInfo = SxBuiltinInfo {
     name:       "head",
     min_arity:  1,
     max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "list", ty: SxType::NonEmptyList }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_head)
 };
 
@@ -121,13 +445,193 @@ static PRIMITIVE_TAIL: SxBuiltinInfo = SxBuiltinInfo {
     name:       "tail",
     min_arity:  1,
     max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "list", ty: SxType::NonEmptyList }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_tail)
 };
 
+static PRIMITIVE_GET: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "get",
+    min_arity:  2,
+    max_arity:  Some(3),
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map },
+        SxParam { name: "key", ty: SxType::Any },
+        SxParam { name: "default", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_get)
+};
+
+static PRIMITIVE_ASSOC: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "assoc",
+    min_arity:  3,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map },
+        SxParam { name: "key", ty: SxType::Any },
+        SxParam { name: "value", ty: SxType::Any },
+        SxParam { name: "kvs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_assoc)
+};
+
+static PRIMITIVE_DISSOC: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "dissoc",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map },
+        SxParam { name: "keys", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_dissoc)
+};
+
+static PRIMITIVE_KEYS: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "keys",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_keys)
+};
+
+static PRIMITIVE_VALS: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "vals",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_vals)
+};
+
+static PRIMITIVE_CONTAINS: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "contains?",
+    min_arity:  2,
+    max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "map", ty: SxType::Map },
+        SxParam { name: "key", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_contains)
+};
+
+static PRIMITIVE_CONJ: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "conj",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "xs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_conj)
+};
+
+static PRIMITIVE_DISJ: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "disj",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "xs", ty: SxType::Any }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_disj)
+};
+
+static PRIMITIVE_UNION: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "union",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "sets", ty: SxType::Set }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_union)
+};
+
+static PRIMITIVE_INTERSECTION: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "intersection",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "sets", ty: SxType::Set }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_intersection)
+};
+
+static PRIMITIVE_DIFFERENCE: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "difference",
+    min_arity:  1,
+    max_arity:  None,
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "sets", ty: SxType::Set }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_difference)
+};
+
+static PRIMITIVE_SUBSET: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "subset?",
+    min_arity:  2,
+    max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "set", ty: SxType::Set },
+        SxParam { name: "superset", ty: SxType::Set }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_subset)
+};
+
+static PRIMITIVE_CHAR_TO_INT: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "char->int",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "c", ty: SxType::ScalarChar }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_char_to_int)
+};
+
+static PRIMITIVE_INT_TO_CHAR: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "int->char",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "code", ty: SxType::ScalarValue }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_int_to_char)
+};
+
+static PRIMITIVE_STRING_TO_CHARS: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "string->chars",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "s", ty: SxType::String }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_string_to_chars)
+};
+
+static PRIMITIVE_CHARS_TO_STRING: SxBuiltinInfo = SxBuiltinInfo {
+    name:       "chars->string",
+    min_arity:  1,
+    max_arity:  Some(1),
+    params:     &[
+        SxParam { name: "chars", ty: SxType::CharSequence }
+    ],
+    callback:   SxBuiltinCallback::Primitive(primitive_chars_to_string)
+};
+
 static PRIMITIVE_EQ: SxBuiltinInfo = SxBuiltinInfo {
     name:       "=",
     min_arity:  1,
     max_arity:  None,
+    params:     &[
+        SxParam { name: "x", ty: SxType::Any },
+        SxParam { name: "ys", ty: SxType::Any }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_eq)
 };
 
@@ -135,6 +639,9 @@ static PRIMITIVE_PLUS: SxBuiltinInfo = SxBuiltinInfo {
     name:       "+",
     min_arity:  0,
     max_arity:  None,
+    params:     &[
+        SxParam { name: "xs", ty: SxType::Number }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_plus)
 };
 
@@ -142,6 +649,10 @@ static PRIMITIVE_MINUS: SxBuiltinInfo = SxBuiltinInfo {
     name:       "-",
     min_arity:  1,
     max_arity:  None,
+    params:     &[
+        SxParam { name: "x", ty: SxType::Number },
+        SxParam { name: "ys", ty: SxType::Number }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_minus)
 };
 
@@ -149,13 +660,20 @@ static PRIMITIVE_PRODUCT: SxBuiltinInfo = SxBuiltinInfo {
     name:       "*",
     min_arity:  0,
     max_arity:  None,
+    params:     &[
+        SxParam { name: "xs", ty: SxType::Number }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_product)
 };
 
 static PRIMITIVE_RANGE: SxBuiltinInfo = SxBuiltinInfo {
     name:       "range",
-    min_arity:  0,
+    min_arity:  1,
     max_arity:  Some(2),
+    params:     &[
+        SxParam { name: "n", ty: SxType::Integer },
+        SxParam { name: "end", ty: SxType::Integer }
+    ],
     callback:   SxBuiltinCallback::Primitive(primitive_range)
 };
 
@@ -168,6 +686,10 @@ fn special_def(env: &mut Env, args: &[Sx]) -> Result {
                 None    => ()
             }
 
+            if env.lookup_macro(&env.current_module, symbol).is_some() {
+                return Err(Error::Redefine(symbol.clone()));
+            }
+
             match env.lookup_current(symbol) {
                 None => {
                     let value = &args[1];
@@ -195,6 +717,66 @@ fn special_def(env: &mut Env, args: &[Sx]) -> Result {
     }
 }
 
+// Defines a macro in the current module, which is a function that is called
+// with the unevaluated arguments of a call and returns the form to evaluate
+// in its place, e.g. (defmacro swap (x y) `(,y ,x)).
+fn special_defmacro(env: &mut Env, args: &[Sx]) -> Result {
+    let name = &args[0];
+    let symbol = match name {
+        Sx::Symbol(symbol) => symbol,
+        _ => return Err(Error::DefineBadSymbol(name.clone()))
+    };
+
+    if env.lookup_core(symbol).is_some() {
+        return Err(Error::RedefineCore(symbol.clone()));
+    }
+
+    if env.lookup_current(symbol).is_some() || env.lookup_macro(&env.current_module, symbol).is_some() {
+        return Err(Error::Redefine(symbol.clone()));
+    }
+
+    match args[1] {
+        Sx::List(_) => (),
+        ref invalid => return Err(bad_arg(&SPECIAL_DEFMACRO, 1, invalid))
+    }
+
+    match special_fn(env, &args[1 ..]) {
+        Ok(Sx::Function(ref f)) => {
+            env.define_macro(symbol, f);
+            return Ok(name.clone());
+        },
+
+        Ok(_) => {
+            assert!(false);
+            return Ok(sx_nil!());
+        },
+
+        error @ Err(_) => {
+            return error;
+        }
+    }
+}
+
+// Registers a function of one argument as the reader of a tag, which is
+// called with the form following the tag in source read afterwards,
+// e.g. (defreader point (fn (v) (tagged 'point v))).
+fn special_defreader(env: &mut Env, args: &[Sx]) -> Result {
+    let tag_arg = &args[0];
+    let tag = match tag_arg {
+        Sx::Symbol(tag) => tag.clone(),
+        _ => return Err(bad_arg(&SPECIAL_DEFREADER, 0, tag_arg))
+    };
+
+    let handler = match eval(env, &args[1]) {
+        Ok(handler @ Sx::Builtin(_)) | Ok(handler @ Sx::Function(_)) => handler,
+        Ok(value) => return Err(Error::NotAFunction(value)),
+        error @ Err(_) => return error
+    };
+
+    env.readers.insert(tag.to_string(), handler);
+    return Ok(tag_arg.clone());
+}
+
 // TODO: vector binding
 fn special_fn(env: &mut Env, args: &[Sx]) -> Result {
     let binding_list = &args[0];
@@ -222,102 +804,545 @@ fn special_fn(env: &mut Env, args: &[Sx]) -> Result {
                 body.push(args[i].clone());
             }
 
+            warn_bindings(env, &bindings_vec, &body);
+
             let f = SxFunctionInfo {
                 module:     env.current_module.clone(),
                 arity:      bindings.len(),
                 bindings:   bindings_vec,
-                body:       Arc::new(body)
+                body:       Arc::new(body),
+                scope:      env.scope.clone()
             };
 
             return Ok(Sx::Function(Arc::new(f)));
         },
 
         _ => {
-            return Err(Error::BuiltinBadArg(SPECIAL_FN.name, binding_list.clone()));
+            return Err(bad_arg(&SPECIAL_FN, 0, binding_list));
         }
     }
 }
 
-fn special_if(env: &mut Env, args: &[Sx]) -> Result {
-    let cond = &args[0];
-    let true_path = &args[1];
-    let false_path = &args[2];
-
-    match eval(env, cond) {
-        Ok(Sx::Nil) | Ok(Sx::Boolean(false)) => {
-            return eval(env, false_path);
-        },
-
-        Ok(_) => {
-            return eval(env, true_path);
-        },
+// Local bindings hide definitions of the same name, including core ones.
+fn warn_bindings(env: &Env, names: &[SxSymbol], body: &[Sx]) {
+    for name in names.iter() {
+        if env.lookup_core(name).is_some() {
+            env.warn(Warning::ShadowDefinition(name.clone(), env.core_module.clone()));
+        } else if env.lookup_current(name).is_some() {
+            env.warn(Warning::ShadowDefinition(name.clone(), env.current_module.clone()));
+        }
 
-        error @ Err(_) => {
-            return error;
+        if !name.starts_with('_') && !body.iter().any(|sx| mentions(sx, name)) {
+            env.warn(Warning::UnusedBinding(name.clone()));
         }
     }
 }
 
-fn special_module(env: &mut Env, args: &[Sx]) -> Result {
-    let module_name_arg = &args[0];
-    let module_name = match module_name_arg {
-        Sx::Symbol(module_name) => module_name,
-        _ => return Err(Error::BuiltinBadArg(SPECIAL_MODULE.name, module_name_arg.clone()))
+// Whether a symbol occurs anywhere in a form, quoted or not.
+fn mentions(sx: &Sx, symbol: &SxSymbol) -> bool {
+    match sx {
+        Sx::Symbol(s) => return s == symbol,
+        Sx::List(sxs) => return sxs.iter().any(|sx| mentions(sx, symbol)),
+        Sx::Vector(sxs) => return sxs.iter().any(|sx| mentions(sx, symbol)),
+        Sx::Map(m) => return m.iter().any(|(key, value)| mentions(key, symbol) || mentions(value, symbol)),
+        Sx::Set(set) => return set.0.iter().any(|sx| mentions(sx, symbol)),
+        Sx::Tagged(t) => return mentions(&t.value, symbol),
+        Sx::Quote(quoted) => return mentions(quoted, symbol),
+        _ => return false
+    }
+}
+
+// Which values of a let form see its bindings.
+#[derive(Clone, Copy, PartialEq)]
+enum LetKind {
+    Parallel,
+    Sequential,
+    Recursive
+}
+
+// Parses let bindings such as ((x 1) (y (+ x 1))) into names and the forms
+// of their values, warning about them like function parameters. Names may
+// only repeat when bound one by one.
+fn let_bindings(builtin: &SxBuiltinInfo, kind: LetKind, env: &Env, args: &[Sx]) -> result::Result<Vec<(SxSymbol, Sx)>, Error> {
+    let binding_list = match args[0] {
+        Sx::List(ref binding_list) => binding_list,
+        ref invalid => return Err(bad_arg(builtin, 0, invalid))
     };
 
-    env.loaded_modules.insert(module_name.clone());
-    env.current_module = module_name.clone();
+    let mut bindings: Vec<(SxSymbol, Sx)> = Vec::new();
+    for binding in binding_list.iter() {
+        match binding {
+            Sx::List(pair) => match pair[..] {
+                [Sx::Symbol(ref name), ref value] => {
+                    if kind != LetKind::Sequential && bindings.iter().any(|(bound, _)| bound == name) {
+                        return Err(Error::DuplicateBinding(name.clone()));
+                    }
 
-    return Ok(Sx::Symbol(module_name.clone()));
+                    bindings.push((name.clone(), value.clone()));
+                },
+
+                _ => return Err(Error::InvalidBinding(binding.clone()))
+            },
+
+            invalid => {
+                return Err(Error::InvalidBinding(invalid.clone()));
+            }
+        }
+    }
+
+    for (index, (name, _)) in bindings.iter().enumerate() {
+        let visible = match kind {
+            LetKind::Parallel => &bindings[.. 0],
+            LetKind::Sequential => &bindings[index + 1 ..],
+            LetKind::Recursive => &bindings[..]
+        };
+
+        let mut body = visible
+            .iter()
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>();
+        body.extend_from_slice(&args[1 ..]);
+        warn_bindings(env, slice::from_ref(name), &body);
+    }
+
+    return Ok(bindings);
 }
 
-fn special_quote(_env: &mut Env, args: &[Sx]) -> Result {
-    return Ok(args[0].clone());
+// Evaluates a let body with the scope of its bindings. Only the scope
+// differs from the enclosing environment, so that definitions are kept.
+fn let_body(env: &mut Env, scope: Scope, body: &[Sx]) -> TailResult {
+    let outer_scope = mem::replace(&mut env.scope, scope);
+    let result = eval_body(env, body);
+    let scope = mem::replace(&mut env.scope, outer_scope);
+
+    match result {
+        Ok(Tail::Eval(last_expr)) => return Ok(Tail::EvalScoped(scope, last_expr)),
+        result => return result
+    }
 }
 
-fn special_use(env: &mut Env, args: &[Sx]) -> Result {
-    let module_arg = &args[0];
-    match module_arg {
-        Sx::Symbol(ref module_name) => {
-            return module::load_use(env, module_name);
-        },
+// Values are evaluated in the enclosing scope, so that none of them sees the
+// others.
+fn special_let(env: &mut Env, args: &[Sx]) -> TailResult {
+    let bindings = let_bindings(&SPECIAL_LET, LetKind::Parallel, env, args)?;
 
-        _ => {
-            return Err(Error::BuiltinBadArg(SPECIAL_USE.name, module_arg.clone()));
+    let mut values = Vec::new();
+    for (name, value) in bindings.into_iter() {
+        match eval(env, &value) {
+            Ok(result) => values.push((name, result)),
+            Err(error) => return Err(error)
         }
     }
+
+    let scope = env.scope.extend(values);
+    return let_body(env, scope, &args[1 ..]);
 }
 
-// TODO: generic iterators
-fn primitive_apply(env: &mut Env, args: &[Sx]) -> Result {
-    let head = &args[0];
-    let value = &args[1];
-    match (eval(env, head), value) {
-        (Ok(Sx::Builtin(builtin)), Sx::List(sub_args)) => {
-            return apply_builtin(builtin, env, sub_args);
-        },
+// Each value sees the bindings before it.
+fn special_let_star(env: &mut Env, args: &[Sx]) -> TailResult {
+    let bindings = let_bindings(&SPECIAL_LET_STAR, LetKind::Sequential, env, args)?;
 
-        (Ok(Sx::Function(ref f)), Sx::List(sub_args)) => {
-            return apply_function(f, env, sub_args);
+    let mut scope = env.scope.clone();
+    for (name, value) in bindings.into_iter() {
+        match eval_scoped(env, &scope, &value) {
+            Ok(result) => scope = scope.extend(vec![(name, result)]),
+            Err(error) => return Err(error)
         }
+    }
 
-        (Ok(Sx::Builtin(_)), value) => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_APPLY.name, value.clone()));
-        },
+    return let_body(env, scope, &args[1 ..]);
+}
 
-        (Ok(v), _) => {
-            return Err(Error::NotAFunction(v.clone()));
-        },
+// Values are fn forms evaluated in the new scope, so that the functions can
+// refer to each other, and none of them is called before all are bound.
+fn special_letrec(env: &mut Env, args: &[Sx]) -> TailResult {
+    let bindings = let_bindings(&SPECIAL_LETREC, LetKind::Recursive, env, args)?;
 
-        (error @ Err(_), _) => {
-            return error;
+    for (name, value) in bindings.iter() {
+        if !is_fn_form(env, value) {
+            return Err(Error::RecursiveBindingNotFn(name.clone(), value.clone()));
         }
     }
-}
 
-// TODO: iterator magic
-fn primitive_env(env: &mut Env, _args: &[Sx]) -> Result {
-    let mut module_paths = Vec::new();
+    // The functions hold the new scope weakly, so that they do not keep the
+    // scope that holds them alive.
+    let scope = env.scope.extend_recursive();
+    let weak_scope = scope.weak();
+
+    let mut values = Vec::new();
+    for (name, value) in bindings.into_iter() {
+        match eval_scoped(env, &weak_scope, &value) {
+            Ok(result) => values.push((name, result)),
+            Err(error) => return Err(error)
+        }
+    }
+
+    scope.bind_recursive(values);
+    return let_body(env, scope, &args[1 ..]);
+}
+
+// Whether a form creates a function, such as (fn (n) (+ n 1)).
+fn is_fn_form(env: &mut Env, sx: &Sx) -> bool {
+    match sx {
+        Sx::List(l) => match l.first() {
+            Some(head @ Sx::Symbol(_)) => {
+                return matches!(eval(env, head), Ok(Sx::Builtin(builtin)) if builtin.name == SPECIAL_FN.name);
+            },
+
+            _ => return false
+        },
+
+        _ => return false
+    }
+}
+
+// Only nil and false are false.
+fn is_true(sx: &Sx) -> bool {
+    match sx {
+        Sx::Nil | Sx::Boolean(false) => return false,
+        _ => return true
+    }
+}
+
+// Evaluates all but the last expression of a body, leaving the last in tail
+// position.
+fn eval_body(env: &mut Env, body: &[Sx]) -> TailResult {
+    let (last_expr, exprs) = match body.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Value(sx_nil!()))
+    };
+
+    for expr in exprs.iter() {
+        match eval(env, expr) {
+            Ok(_) => (),
+            Err(error) => return Err(error)
+        }
+    }
+
+    return Ok(Tail::Eval(last_expr.clone()));
+}
+
+fn special_if(env: &mut Env, args: &[Sx]) -> TailResult {
+    let cond = &args[0];
+    let true_path = &args[1];
+    let false_path = &args[2];
+
+    match eval(env, cond) {
+        Ok(ref result) if is_true(result) => {
+            return Ok(Tail::Eval(true_path.clone()));
+        },
+
+        Ok(_) => {
+            return Ok(Tail::Eval(false_path.clone()));
+        },
+
+        Err(error) => {
+            return Err(error);
+        }
+    }
+}
+
+fn special_do(env: &mut Env, args: &[Sx]) -> TailResult {
+    return eval_body(env, args);
+}
+
+// Clauses are lists of a test and a body, where the test else always
+// passes. A clause without a body returns the value of its test.
+fn special_cond(env: &mut Env, args: &[Sx]) -> TailResult {
+    for (index, clause) in args.iter().enumerate() {
+        let clause = match clause {
+            Sx::List(clause) if !clause.is_empty() => clause,
+            invalid => return Err(bad_arg(&SPECIAL_COND, index, invalid))
+        };
+
+        let test = &clause[0];
+        let body = &clause[1 ..];
+
+        match test {
+            Sx::Symbol(symbol) if symbol.as_str() == "else" => {
+                return eval_body(env, body);
+            },
+
+            _ => ()
+        }
+
+        match eval(env, test) {
+            Ok(ref result) if is_true(result) && body.is_empty() => {
+                return Ok(Tail::Value(result.clone()));
+            },
+
+            Ok(ref result) if is_true(result) => {
+                return eval_body(env, body);
+            },
+
+            Ok(_) => (),
+
+            Err(error) => {
+                return Err(error);
+            }
+        }
+    }
+
+    return Ok(Tail::Value(sx_nil!()));
+}
+
+fn special_when(env: &mut Env, args: &[Sx]) -> TailResult {
+    match eval(env, &args[0]) {
+        Ok(ref result) if is_true(result) => return eval_body(env, &args[1 ..]),
+        Ok(_) => return Ok(Tail::Value(sx_nil!())),
+        Err(error) => return Err(error)
+    }
+}
+
+fn special_unless(env: &mut Env, args: &[Sx]) -> TailResult {
+    match eval(env, &args[0]) {
+        Ok(ref result) if is_true(result) => return Ok(Tail::Value(sx_nil!())),
+        Ok(_) => return eval_body(env, &args[1 ..]),
+        Err(error) => return Err(error)
+    }
+}
+
+// Returns the first false value without evaluating the rest, or else the
+// value of the last expression, which is true for no expressions.
+fn special_and(env: &mut Env, args: &[Sx]) -> TailResult {
+    let (last_expr, exprs) = match args.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Value(sx_boolean!(true)))
+    };
+
+    for expr in exprs.iter() {
+        match eval(env, expr) {
+            Ok(ref result) if !is_true(result) => return Ok(Tail::Value(result.clone())),
+            Ok(_) => (),
+            Err(error) => return Err(error)
+        }
+    }
+
+    return Ok(Tail::Eval(last_expr.clone()));
+}
+
+// Returns the first true value without evaluating the rest, or else the
+// value of the last expression, which is nil for no expressions.
+fn special_or(env: &mut Env, args: &[Sx]) -> TailResult {
+    let (last_expr, exprs) = match args.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Value(sx_nil!()))
+    };
+
+    for expr in exprs.iter() {
+        match eval(env, expr) {
+            Ok(ref result) if is_true(result) => return Ok(Tail::Value(result.clone())),
+            Ok(_) => (),
+            Err(error) => return Err(error)
+        }
+    }
+
+    return Ok(Tail::Eval(last_expr.clone()));
+}
+
+fn special_module(env: &mut Env, args: &[Sx]) -> Result {
+    let module_name_arg = &args[0];
+    let module_name = match module_name_arg {
+        Sx::Symbol(module_name) => module_name,
+        _ => return Err(bad_arg(&SPECIAL_MODULE, 0, module_name_arg))
+    };
+
+    env.loaded_modules.insert(module_name.clone());
+    env.current_module = module_name.clone();
+
+    return Ok(Sx::Symbol(module_name.clone()));
+}
+
+fn special_quasiquote(env: &mut Env, args: &[Sx]) -> Result {
+    return quasiquote(env, &args[0], 1);
+}
+
+// Returns the argument of a (name x) form, used to detect unquotes and
+// nested quasiquotes within a template.
+fn template_arg<'a>(sx: &'a Sx, name: &str) -> Option<&'a Sx> {
+    match sx {
+        Sx::List(sxs) if sxs.len() == 2 => {
+            match &sxs[0] {
+                Sx::Symbol(symbol) if symbol.as_str() == name => return Some(&sxs[1]),
+                _ => return None
+            }
+        },
+
+        _ => return None
+    }
+}
+
+// Expands a quasiquote template. Unquotes are evaluated only at depth 1,
+// each nested quasiquote adding a level and each unquote removing one.
+fn quasiquote(env: &mut Env, sx: &Sx, depth: usize) -> Result {
+    if let Some(arg) = template_arg(sx, SPECIAL_UNQUOTE.name) {
+        if depth == 1 {
+            return eval(env, arg);
+        }
+
+        match quasiquote(env, arg, depth - 1) {
+            Ok(result) => return Ok(sx_list![sx_symbol!(SPECIAL_UNQUOTE.name), result]),
+            error @ Err(_) => return error
+        }
+    }
+
+    if let Some(arg) = template_arg(sx, SPECIAL_QUASIQUOTE.name) {
+        match quasiquote(env, arg, depth + 1) {
+            Ok(result) => return Ok(sx_list![sx_symbol!(SPECIAL_QUASIQUOTE.name), result]),
+            error @ Err(_) => return error
+        }
+    }
+
+    if template_arg(sx, SPECIAL_UNQUOTE_SPLICING.name).is_some() && depth == 1 {
+        return Err(Error::SpliceOutsideSequence(sx.clone()));
+    }
+
+    match sx {
+        Sx::List(sxs) => {
+            match quasiquote_elements(env, sxs.iter(), depth) {
+                Ok(results) => return Ok(sx_list_from_vec!(results)),
+                Err(error) => return Err(error)
+            }
+        },
+
+        Sx::Vector(sxs) => {
+            match quasiquote_elements(env, sxs.iter(), depth) {
+                Ok(results) => return Ok(sx_vector_from_vec!(results)),
+                Err(error) => return Err(error)
+            }
+        },
+
+        Sx::Quote(quoted) => {
+            match quasiquote(env, quoted, depth) {
+                Ok(result) => return Ok(sx_quote!(result)),
+                error @ Err(_) => return error
+            }
+        },
+
+        _ => return Ok(sx.clone())
+    }
+}
+
+// Expands the elements of a list or vector template, splicing the contents
+// of unquote-splicing forms at depth 1.
+fn quasiquote_elements<'a, I>(env: &mut Env, sxs: I, depth: usize) -> result::Result<Vec<Sx>, Error>
+    where I: Iterator<Item = &'a Sx>
+{
+    let mut results = Vec::new();
+    for sx in sxs {
+        let splice_arg = match template_arg(sx, SPECIAL_UNQUOTE_SPLICING.name) {
+            Some(arg) if depth == 1 => arg,
+            Some(arg) => {
+                match quasiquote(env, arg, depth - 1) {
+                    Ok(result) => results.push(sx_list![sx_symbol!(SPECIAL_UNQUOTE_SPLICING.name), result]),
+                    Err(error) => return Err(error)
+                }
+
+                continue;
+            },
+
+            None => {
+                match quasiquote(env, sx, depth) {
+                    Ok(result) => results.push(result),
+                    Err(error) => return Err(error)
+                }
+
+                continue;
+            }
+        };
+
+        match eval(env, splice_arg) {
+            Ok(Sx::List(spliced)) => results.extend(spliced.iter().cloned()),
+            Ok(Sx::Vector(spliced)) => results.extend(spliced.iter().cloned()),
+            Ok(Sx::Nil) => (),
+            Ok(value) => return Err(Error::BuiltinBadArg(SPECIAL_UNQUOTE_SPLICING.name, 0, SxType::Sequence, value)),
+            Err(error) => return Err(error)
+        }
+    }
+
+    return Ok(results);
+}
+
+fn special_quote(_env: &mut Env, args: &[Sx]) -> Result {
+    return Ok(args[0].clone());
+}
+
+fn special_unquote(_env: &mut Env, args: &[Sx]) -> Result {
+    return Err(Error::UnquoteOutsideQuasiquote(sx_list![sx_symbol!(SPECIAL_UNQUOTE.name), args[0].clone()]));
+}
+
+fn special_unquote_splicing(_env: &mut Env, args: &[Sx]) -> Result {
+    return Err(Error::UnquoteOutsideQuasiquote(sx_list![sx_symbol!(SPECIAL_UNQUOTE_SPLICING.name), args[0].clone()]));
+}
+
+fn special_use(env: &mut Env, args: &[Sx]) -> Result {
+    let module_arg = &args[0];
+    match module_arg {
+        Sx::Symbol(ref module_name) => {
+            return module::load_use(env, module_name);
+        },
+
+        _ => {
+            return Err(bad_arg(&SPECIAL_USE, 0, module_arg));
+        }
+    }
+}
+
+// TODO: generic iterators
+fn primitive_apply(env: &mut Env, args: &[Sx]) -> Result {
+    let head = &args[0];
+    let value = &args[1];
+    match (eval(env, head), value) {
+        (Ok(Sx::Builtin(builtin)), Sx::List(sub_args)) => {
+            return apply_builtin(builtin, env, sub_args);
+        },
+
+        (Ok(Sx::Function(ref f)), Sx::List(sub_args)) => {
+            return apply_function(f, env, sub_args);
+        }
+
+        (Ok(Sx::Builtin(_)), value) => {
+            return Err(bad_arg(&PRIMITIVE_APPLY, 1, value));
+        },
+
+        (Ok(v), _) => {
+            return Err(Error::NotAFunction(v.clone()));
+        },
+
+        (error @ Err(_), _) => {
+            return error;
+        }
+    }
+}
+
+// Describes how to call a function, e.g. (doc get) returns
+// "(get map key [default])".
+fn primitive_doc(_env: &mut Env, args: &[Sx]) -> Result {
+    let f_arg = &args[0];
+    match f_arg {
+        Sx::Builtin(builtin) => {
+            return Ok(sx_string!(builtin.signature()));
+        },
+
+        Sx::Function(f) => {
+            let bindings = f.bindings
+                .iter()
+                .map(|binding| binding.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            return Ok(sx_string!(format!("(fn ({}) ...)", bindings)));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_DOC, 0, f_arg));
+        }
+    }
+}
+
+// TODO: iterator magic
+fn primitive_env(env: &mut Env, _args: &[Sx]) -> Result {
+    let mut module_paths = Vec::new();
     for module_path in env.module_paths.iter() {
         module_paths.push(sx_string!(module_path));
     }
@@ -335,20 +1360,48 @@ fn primitive_env(env: &mut Env, _args: &[Sx]) -> Result {
     }
 
     return Ok(sx_vector![
-        sx_vector![sx_symbol!("module-paths"), sx_vector_from_vec!(module_paths)],
-        sx_vector![sx_symbol!("current-module"), Sx::Symbol(current_module)],
-        sx_vector![sx_symbol!("loaded-modules"), sx_vector_from_vec!(loaded_modules)],
-        sx_vector![sx_symbol!("definitions"), sx_vector_from_vec!(env_list)]
+        sx_vector![sx_keyword!("module-paths"), sx_vector_from_vec!(module_paths)],
+        sx_vector![sx_keyword!("current-module"), Sx::Symbol(current_module)],
+        sx_vector![sx_keyword!("loaded-modules"), sx_vector_from_vec!(loaded_modules)],
+        sx_vector![sx_keyword!("definitions"), sx_vector_from_vec!(env_list)]
     ]);
 }
 
+fn primitive_gensym(env: &mut Env, args: &[Sx]) -> Result {
+    let prefix = match args.first() {
+        Some(Sx::String(prefix)) => prefix.as_str(),
+        _ => "g"
+    };
+
+    return Ok(Sx::Symbol(env.gensym(prefix)));
+}
+
+// Expands the form until it is no longer a macro call.
+fn primitive_macroexpand(env: &mut Env, args: &[Sx]) -> Result {
+    let mut form = args[0].clone();
+    loop {
+        match expand_macro(env, &form) {
+            Some(Ok(expansion)) => form = expansion,
+            Some(error @ Err(_)) => return error,
+            None => return Ok(form)
+        }
+    }
+}
+
+fn primitive_macroexpand_1(env: &mut Env, args: &[Sx]) -> Result {
+    match expand_macro(env, &args[0]) {
+        Some(result) => return result,
+        None => return Ok(args[0].clone())
+    }
+}
+
 fn primitive_trace(_env: &mut Env, args: &[Sx]) -> Result {
     let ts = time::now();
 
     let label_arg = &args[0];
     let label = match label_arg {
         Sx::String(s) => s,
-        _ => return Err(Error::BuiltinBadArg(PRIMITIVE_TRACE.name, label_arg.clone()))
+        _ => return Err(bad_arg(&PRIMITIVE_TRACE, 0, label_arg))
     };
 
     let value = &args[1];
@@ -365,6 +1418,30 @@ Timestamp: {}
     return Ok(value.clone());
 }
 
+fn primitive_tagged(_env: &mut Env, args: &[Sx]) -> Result {
+    let tag_arg = &args[0];
+    match tag_arg {
+        Sx::Symbol(tag) => return Ok(sx_tagged!(tag, args[1].clone())),
+        _ => return Err(bad_arg(&PRIMITIVE_TAGGED, 0, tag_arg))
+    }
+}
+
+fn primitive_tag(_env: &mut Env, args: &[Sx]) -> Result {
+    let tagged_arg = &args[0];
+    match tagged_arg {
+        Sx::Tagged(t) => return Ok(Sx::Symbol(t.tag.clone())),
+        _ => return Err(bad_arg(&PRIMITIVE_TAG, 0, tagged_arg))
+    }
+}
+
+fn primitive_untag(_env: &mut Env, args: &[Sx]) -> Result {
+    let tagged_arg = &args[0];
+    match tagged_arg {
+        Sx::Tagged(t) => return Ok(t.value.clone()),
+        _ => return Err(bad_arg(&PRIMITIVE_UNTAG, 0, tagged_arg))
+    }
+}
+
 // TODO: vector
 fn primitive_cons(_env: &mut Env, args: &[Sx]) -> Result {
     let value = &args[0];
@@ -378,7 +1455,7 @@ fn primitive_cons(_env: &mut Env, args: &[Sx]) -> Result {
         },
 
         _ => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_CONS.name, list_arg.clone()));
+            return Err(bad_arg(&PRIMITIVE_CONS, 1, list_arg));
         }
     }
 }
@@ -394,13 +1471,13 @@ fn primitive_head(_env: &mut Env, args: &[Sx]) -> Result {
                 },
 
                 None => {
-                    return Err(Error::BuiltinBadArg(PRIMITIVE_HEAD.name, list_arg.clone()));
+                    return Err(bad_arg(&PRIMITIVE_HEAD, 0, list_arg));
                 }
             }
         },
 
         _ => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_HEAD.name, list_arg.clone()));
+            return Err(bad_arg(&PRIMITIVE_HEAD, 0, list_arg));
         }
     }
 }
@@ -417,15 +1494,275 @@ fn primitive_tail(_env: &mut Env, args: &[Sx]) -> Result {
                 },
 
                 None => {
-                    return Err(Error::BuiltinBadArg(PRIMITIVE_TAIL.name, list_arg.clone()));
+                    return Err(bad_arg(&PRIMITIVE_TAIL, 0, list_arg));
                 }
             }
         },
 
         _ => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_TAIL.name, list_arg.clone()));
+            return Err(bad_arg(&PRIMITIVE_TAIL, 0, list_arg));
+        }
+    }
+}
+
+// Looks up a key in a map, returning the given default or nil if the key
+// is missing.
+fn primitive_get(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    let key = &args[1];
+    match map_arg {
+        Sx::Map(m) => {
+            match (m.get(key), args.get(2)) {
+                (Some(value), _) => return Ok(value.clone()),
+                (None, Some(default)) => return Ok(default.clone()),
+                (None, None) => return Ok(sx_nil!())
+            }
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_GET, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_assoc(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    let entries = &args[1 ..];
+    match map_arg {
+        Sx::Map(m) => {
+            if !entries.len().is_multiple_of(2) {
+                return Err(Error::BuiltinUnpairedKey(PRIMITIVE_ASSOC.name, entries[entries.len() - 1].clone()));
+            }
+
+            let mut new_map = m.as_ref().clone();
+            for entry in entries.chunks(2) {
+                new_map.insert(entry[0].clone(), entry[1].clone());
+            }
+
+            return Ok(Sx::Map(Arc::new(new_map)));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_ASSOC, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_dissoc(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    match map_arg {
+        Sx::Map(m) => {
+            let mut new_map = m.as_ref().clone();
+            for key in args[1 ..].iter() {
+                new_map.remove(key);
+            }
+
+            return Ok(Sx::Map(Arc::new(new_map)));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_DISSOC, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_keys(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    match map_arg {
+        Sx::Map(m) => {
+            return Ok(Sx::Vector(Arc::new(m.keys().cloned().collect())));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_KEYS, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_vals(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    match map_arg {
+        Sx::Map(m) => {
+            return Ok(Sx::Vector(Arc::new(m.values().cloned().collect())));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_VALS, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_contains(_env: &mut Env, args: &[Sx]) -> Result {
+    let map_arg = &args[0];
+    let key = &args[1];
+    match map_arg {
+        Sx::Map(m) => {
+            return Ok(Sx::Boolean(m.contains_key(key)));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_CONTAINS, 0, map_arg));
+        }
+    }
+}
+
+fn primitive_conj(_env: &mut Env, args: &[Sx]) -> Result {
+    let set_arg = &args[0];
+    match set_arg {
+        Sx::Set(set) => {
+            let mut new_set = set.0.as_ref().clone();
+            for sx in args[1 ..].iter() {
+                new_set.insert(sx.clone());
+            }
+
+            return Ok(Sx::Set(SxSet(Arc::new(new_set))));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_CONJ, 0, set_arg));
+        }
+    }
+}
+
+fn primitive_disj(_env: &mut Env, args: &[Sx]) -> Result {
+    let set_arg = &args[0];
+    match set_arg {
+        Sx::Set(set) => {
+            let mut new_set = set.0.as_ref().clone();
+            for sx in args[1 ..].iter() {
+                new_set.remove(sx);
+            }
+
+            return Ok(Sx::Set(SxSet(Arc::new(new_set))));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_DISJ, 0, set_arg));
+        }
+    }
+}
+
+type SetOp = fn(im::HashSet<Sx>, im::HashSet<Sx>) -> im::HashSet<Sx>;
+
+// Folds a set operation over set arguments, left to right.
+fn fold_sets(builtin: &SxBuiltinInfo, args: &[Sx], op: SetOp) -> Result {
+    let mut acc = match &args[0] {
+        Sx::Set(set) => set.0.as_ref().clone(),
+        arg => return Err(bad_arg(builtin, 0, arg))
+    };
+
+    for (index, arg) in args.iter().enumerate().skip(1) {
+        match arg {
+            Sx::Set(set) => acc = op(acc, set.0.as_ref().clone()),
+            _ => return Err(bad_arg(builtin, index, arg))
+        }
+    }
+
+    return Ok(Sx::Set(SxSet(Arc::new(acc))));
+}
+
+fn primitive_union(_env: &mut Env, args: &[Sx]) -> Result {
+    return fold_sets(&PRIMITIVE_UNION, args, im::HashSet::union);
+}
+
+fn primitive_intersection(_env: &mut Env, args: &[Sx]) -> Result {
+    return fold_sets(&PRIMITIVE_INTERSECTION, args, im::HashSet::intersection);
+}
+
+fn primitive_difference(_env: &mut Env, args: &[Sx]) -> Result {
+    // im::HashSet::difference is the symmetric difference.
+    return fold_sets(&PRIMITIVE_DIFFERENCE, args, |mut x, y| {
+        for sx in y.iter() {
+            x.remove(sx);
+        }
+
+        return x;
+    });
+}
+
+fn primitive_subset(_env: &mut Env, args: &[Sx]) -> Result {
+    match (&args[0], &args[1]) {
+        (Sx::Set(x), Sx::Set(y)) => {
+            return Ok(Sx::Boolean(x.0.is_subset(y.0.as_ref())));
+        },
+
+        (Sx::Set(_), arg) => {
+            return Err(bad_arg(&PRIMITIVE_SUBSET, 1, arg));
+        },
+
+        (arg, _) => {
+            return Err(bad_arg(&PRIMITIVE_SUBSET, 0, arg));
+        }
+    }
+}
+
+// Only characters of a single code point have an integer value.
+fn primitive_char_to_int(_env: &mut Env, args: &[Sx]) -> Result {
+    let char_arg = &args[0];
+    match char_arg {
+        Sx::Char(c) => {
+            let mut chars = c.chars();
+            match (chars.next(), chars.next()) {
+                (Some(only), None) => return Ok(sx_integer!(only as i64)),
+                _ => return Err(bad_arg(&PRIMITIVE_CHAR_TO_INT, 0, char_arg))
+            }
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_CHAR_TO_INT, 0, char_arg));
+        }
+    }
+}
+
+fn primitive_int_to_char(_env: &mut Env, args: &[Sx]) -> Result {
+    let int_arg = &args[0];
+    let opt_c = match int_arg {
+        Sx::Integer(i) if *i >= 0 && *i <= u32::MAX as i64 => ::std::char::from_u32(*i as u32),
+        _ => None
+    };
+
+    match opt_c {
+        Some(c) => return Ok(sx_char!(c)),
+        None => return Err(bad_arg(&PRIMITIVE_INT_TO_CHAR, 0, int_arg))
+    }
+}
+
+// Splits a string into grapheme clusters, so that e.g. a letter followed by
+// a combining accent stays a single character.
+fn primitive_string_to_chars(_env: &mut Env, args: &[Sx]) -> Result {
+    let string_arg = &args[0];
+    match string_arg {
+        Sx::String(s) => {
+            let chars = s.graphemes(true)
+                .map(|grapheme| sx_char!(grapheme))
+                .collect::<Vec<_>>();
+
+            return Ok(sx_vector_from_vec!(chars));
+        },
+
+        _ => {
+            return Err(bad_arg(&PRIMITIVE_STRING_TO_CHARS, 0, string_arg));
+        }
+    }
+}
+
+fn primitive_chars_to_string(_env: &mut Env, args: &[Sx]) -> Result {
+    let chars_arg = &args[0];
+    let chars = match chars_arg {
+        Sx::List(sxs) => sxs.iter().collect::<Vec<_>>(),
+        Sx::Vector(sxs) => sxs.iter().collect::<Vec<_>>(),
+        _ => return Err(bad_arg(&PRIMITIVE_CHARS_TO_STRING, 0, chars_arg))
+    };
+
+    let mut s = String::new();
+    for sx in chars {
+        match sx {
+            Sx::Char(c) => s.push_str(c),
+            _ => return Err(bad_arg(&PRIMITIVE_CHARS_TO_STRING, 0, chars_arg))
         }
     }
+
+    return Ok(sx_string!(s));
 }
 
 fn primitive_eq(_env: &mut Env, args: &[Sx]) -> Result {
@@ -452,76 +1789,145 @@ fn primitive_eq(_env: &mut Env, args: &[Sx]) -> Result {
 }
 
 fn primitive_plus(_env: &mut Env, args: &[Sx]) -> Result {
-    let mut sum = 0i64;
+    let mut sum = sx_integer!(0);
     for arg in args.iter() {
-        match arg {
-            Sx::Integer(n) => {
-                sum += n;
-            },
-
-            _ => {
-                return Err(Error::BuiltinBadArg(PRIMITIVE_PLUS.name, arg.clone()));
-            }
+        match numeric_op(NumericOp::Add, &sum, arg) {
+            Ok(result) => sum = result,
+            error @ Err(_) => return error
         }
     }
 
-    // TODO: overflow
-    return Ok(sx_integer!(sum));
+    return Ok(sum);
 }
 
 fn primitive_minus(_env: &mut Env, args: &[Sx]) -> Result {
     let diff_arg = &args[0];
-    match diff_arg {
-        Sx::Integer(x) => {
-            if args.len() == 1 {
-                return Ok(sx_integer!(-x));
-            }
-
-            let mut first = true;
-            let mut diff = *x;
-            for arg in args.iter() {
-                if first {
-                    first = false;
-                    continue;
+    if args.len() == 1 {
+        match diff_arg {
+            Sx::Integer(x) => {
+                match x.checked_neg() {
+                    Some(result) => return Ok(sx_integer!(result)),
+                    None => return Ok(Sx::from_big_integer(-BigInt::from(*x)))
                 }
+            },
 
-                match arg {
-                    Sx::Integer(n) => {
-                        diff -= n;
-                    },
+            Sx::BigInteger(x) => return Ok(Sx::from_big_integer(-x.as_ref())),
+            Sx::Float(x) => return Ok(sx_float!(-x.0)),
+            _ => return Err(bad_arg(&PRIMITIVE_MINUS, 0, diff_arg))
+        }
+    }
 
-                    _ => {
-                        return Err(Error::BuiltinBadArg(PRIMITIVE_MINUS.name, arg.clone()));
-                    }
-                }
+    let mut diff = diff_arg.clone();
+    for arg in args[1 ..].iter() {
+        match numeric_op(NumericOp::Sub, &diff, arg) {
+            Ok(result) => diff = result,
+            error @ Err(_) => return error
+        }
+    }
+
+    return Ok(diff);
+}
+
+fn primitive_product(_env: &mut Env, args: &[Sx]) -> Result {
+    let mut product = sx_integer!(1);
+    for arg in args {
+        match numeric_op(NumericOp::Mul, &product, arg) {
+            Ok(result) => product = result,
+            error @ Err(_) => return error
+        }
+    }
+
+    return Ok(product);
+}
+
+#[derive(Clone, Copy)]
+enum NumericOp {
+    Add,
+    Sub,
+    Mul
+}
+
+// Applies an arithmetic operation to two numbers. Integers that overflow
+// are promoted to big integers, and integers are promoted to floats when
+// the other operand is a float.
+fn numeric_op(op: NumericOp, x: &Sx, y: &Sx) -> Result {
+    match (x, y) {
+        (Sx::Integer(a), Sx::Integer(b)) => {
+            let opt_result = match op {
+                NumericOp::Add => a.checked_add(*b),
+                NumericOp::Sub => a.checked_sub(*b),
+                NumericOp::Mul => a.checked_mul(*b)
+            };
+
+            match opt_result {
+                Some(result) => return Ok(sx_integer!(result)),
+                None => return numeric_op(op, &Sx::BigInteger(Arc::new(BigInt::from(*a))), y)
             }
+        },
 
-            // TODO: overflow
-            return Ok(sx_integer!(diff));
+        (Sx::Float(_), Sx::Integer(_))      |
+        (Sx::Float(_), Sx::BigInteger(_))   |
+        (Sx::Float(_), Sx::Float(_))        |
+        (Sx::Integer(_), Sx::Float(_))      |
+        (Sx::BigInteger(_), Sx::Float(_))   => {
+            let (a, b) = (to_float(x), to_float(y));
+            let result = match op {
+                NumericOp::Add => a + b,
+                NumericOp::Sub => a - b,
+                NumericOp::Mul => a * b
+            };
+
+            return Ok(sx_float!(result));
+        },
+
+        (Sx::Integer(_), Sx::BigInteger(_))     |
+        (Sx::BigInteger(_), Sx::Integer(_))     |
+        (Sx::BigInteger(_), Sx::BigInteger(_))  => {
+            let (a, b) = (to_big_integer(x), to_big_integer(y));
+            let result = match op {
+                NumericOp::Add => a + b,
+                NumericOp::Sub => a - b,
+                NumericOp::Mul => a * b
+            };
+
+            return Ok(Sx::from_big_integer(result));
         },
 
         _ => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_MINUS.name, diff_arg.clone()));
+            // Arguments are checked to be numbers before the call.
+            assert!(false);
+            return Ok(sx_nil!());
         }
     }
 }
 
-fn primitive_product(_env: &mut Env, args: &[Sx]) -> Result {
-    let mut product = 1;
-    for arg in args {
-        match arg {
-            Sx::Integer(n) => {
-                product *= n;
-            },
+fn to_big_integer(sx: &Sx) -> BigInt {
+    match sx {
+        Sx::Integer(i) => return BigInt::from(*i),
+        Sx::BigInteger(i) => return i.as_ref().clone(),
+        _ => {
+            assert!(false);
+            return BigInt::from(0);
+        }
+    }
+}
 
-            _ => {
-                return Err(Error::BuiltinBadArg(PRIMITIVE_PRODUCT.name, arg.clone()));
+fn to_float(sx: &Sx) -> f64 {
+    match sx {
+        Sx::Integer(i) => return *i as f64,
+        Sx::BigInteger(i) => {
+            match (i.to_f64(), i.sign()) {
+                (Some(f), _)            => return f,
+                (None, Sign::Minus)     => return f64::NEG_INFINITY,
+                (None, _)               => return f64::INFINITY
             }
+        },
+        Sx::Float(f) => return f.0,
+        _ => {
+            assert!(false);
+            return f64::NAN;
         }
     }
-
-    // TODO: overflow
-    return Ok(sx_integer!(product));
 }
 
 // TODO: vectors
@@ -541,12 +1947,21 @@ fn primitive_range(_env: &mut Env, args: &[Sx]) -> Result {
             }
         },
 
-        [ref arg, Sx::Integer(_)] => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_RANGE.name, arg.clone()));
+        // Vectors are indexed by machine-sized integers anyway.
+        [ref arg @ Sx::BigInteger(_)] | [ref arg @ Sx::BigInteger(_), _] => {
+            return Err(Error::BuiltinArgOutOfRange(PRIMITIVE_RANGE.name, 0, arg.clone()));
+        },
+
+        [_, ref arg @ Sx::BigInteger(_)] => {
+            return Err(Error::BuiltinArgOutOfRange(PRIMITIVE_RANGE.name, 1, arg.clone()));
+        },
+
+        [ref arg] | [ref arg, Sx::Integer(_)] => {
+            return Err(bad_arg(&PRIMITIVE_RANGE, 0, arg));
         },
 
         [_, ref arg] => {
-            return Err(Error::BuiltinBadArg(PRIMITIVE_RANGE.name, arg.clone()));
+            return Err(bad_arg(&PRIMITIVE_RANGE, 1, arg));
         },
 
         _ => {
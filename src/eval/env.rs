@@ -1,9 +1,15 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
 use std::sync::Arc;
 
 use im;
 
+use ::eval::Warning;
+use ::eval::eval::eval;
+use ::eval::scope::Scope;
 use ::eval::builtin::{BUILTIN_MODULE_NAME, BUILTIN_TABLE};
-use ::sx::{Sx, SxSymbol};
+use ::read::dispatch::DispatchTable;
+use ::sx::{Sx, SxFunction, SxSymbol};
 
 #[derive(Clone)]
 pub struct Env {
@@ -11,6 +17,24 @@ pub struct Env {
     pub current_module: SxSymbol,
     pub loaded_modules: im::HashSet<SxSymbol>,
     pub definitions:    im::HashMap<(SxSymbol, SxSymbol), Sx>,
+    pub macros:         im::HashMap<(SxSymbol, SxSymbol), SxFunction>,
+    pub dispatch:       DispatchTable,
+
+    // Handlers of tags registered with defreader, which are only bound to an
+    // environment when source is read.
+    pub readers:        im::HashMap<String, Sx>,
+
+    // Local bindings of the function call being evaluated, empty at the
+    // top level of a module.
+    pub scope:          Scope,
+
+    // Shared by all copies of the environment, so that warnings raised
+    // within function calls and module loads reach whoever evaluates the
+    // top-level form.
+    pub warnings:       Rc<RefCell<Vec<Warning>>>,
+
+    // Shared as well, so that generated symbols stay unique.
+    pub gensym_count:   Rc<Cell<usize>>,
 
     pub core_module:    SxSymbol
 }
@@ -24,6 +48,15 @@ impl Env {
             current_module: current_module.clone(),
             loaded_modules: hashset!(core_module.clone(), current_module.clone()),
             definitions:    hashmap!(),
+            macros:         hashmap!(),
+            dispatch:       DispatchTable::new(),
+            readers:        hashmap!(),
+
+            scope:          Scope::new(),
+
+            warnings:       Rc::new(RefCell::new(Vec::new())),
+
+            gensym_count:   Rc::new(Cell::new(0)),
 
             core_module:    core_module.clone()
         };
@@ -58,4 +91,52 @@ impl Env {
     pub fn lookup_current(&self, symbol: &SxSymbol) -> Option<&Sx> {
         return self.lookup(&self.current_module, symbol);
     }
+
+    pub fn define_macro(&mut self, symbol: &SxSymbol, f: &SxFunction) {
+        self.macros.insert((self.current_module.clone(), symbol.clone()), f.clone());
+    }
+
+    pub fn lookup_macro(&self, module: &SxSymbol, symbol: &SxSymbol) -> Option<&SxFunction> {
+        return self.macros.get(&(module.clone(), symbol.clone()));
+    }
+
+    // The handlers to read source with. Those registered with defreader run
+    // in the environment as it is when the source is read, so that they see
+    // definitions made after them.
+    pub fn dispatch_table(&self) -> DispatchTable {
+        let mut dispatch = self.dispatch.clone();
+        for (tag, handler) in self.readers.iter() {
+            let handler = handler.clone();
+            let handler_env = self.clone();
+            dispatch.register(tag, move |value| {
+                let call = sx_list![handler.clone(), sx_quote!(value.clone())];
+                match eval(&mut handler_env.clone(), &call) {
+                    Ok(result) => return Ok(result),
+                    Err(error) => return Err(error.to_string())
+                }
+            });
+        }
+
+        return dispatch;
+    }
+
+    // A symbol that differs from all symbols generated before, e.g. x__1.
+    pub fn gensym(&self, prefix: &str) -> SxSymbol {
+        let count = self.gensym_count.get() + 1;
+        self.gensym_count.set(count);
+        return sx_symbol_unwrapped!(format!("{}__{}", prefix, count));
+    }
+
+    // Repeated warnings, e.g. from a function created in a loop, are kept once.
+    pub fn warn(&self, warning: Warning) {
+        let mut warnings = self.warnings.borrow_mut();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+
+    // Removes and returns the warnings raised so far.
+    pub fn take_warnings(&self) -> Vec<Warning> {
+        return self.warnings.replace(Vec::new());
+    }
 }
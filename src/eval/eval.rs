@@ -1,17 +1,96 @@
+use std::mem;
 use std::sync::Arc;
 
-use ::eval::{module, Result, Error};
+use im;
+
+use ::eval::{module, Result, TailResult, Tail, Error};
 use ::eval::env::Env;
+use ::eval::scope::Scope;
 use ::sx::{*};
 
+// Function bodies and the forms left by tail specials are evaluated by
+// continuing the loop rather than by recursion, so that calls in tail
+// position run in constant stack. The local scope of the environment is
+// restored once the form is evaluated, since let bodies replace it.
 pub fn eval(env: &mut Env, sx: &Sx) -> Result {
+    let mut tail_env = None;
+    let mut tail_sx = sx.clone();
+    let mut outer_scope = None;
+    let result = loop {
+        let step = {
+            let env = match tail_env {
+                Some(ref mut tail_env) => tail_env,
+                None => &mut *env
+            };
+
+            match tail_sx {
+                Sx::List(ref l) if !l.is_empty() => match expand_macro(env, &tail_sx) {
+                    Some(expansion) => expansion.map(Tail::Eval),
+                    None => apply(env, &l[0], &l[1 ..])
+                },
+
+                _ => break eval_value(env, &tail_sx)
+            }
+        };
+
+        match step {
+            Ok(Tail::Value(result)) => break Ok(result),
+
+            Ok(Tail::Eval(next_sx)) => {
+                tail_sx = next_sx;
+            },
+
+            Ok(Tail::EvalIn(next_env, next_sx)) => {
+                tail_env = Some(next_env);
+                tail_sx = next_sx;
+            },
+
+            Ok(Tail::EvalScoped(next_scope, next_sx)) => {
+                match tail_env {
+                    Some(ref mut tail_env) => tail_env.scope = next_scope,
+                    None => {
+                        let scope = mem::replace(&mut env.scope, next_scope);
+                        outer_scope.get_or_insert(scope);
+                    }
+                }
+
+                tail_sx = next_sx;
+            },
+
+            Err(error) => break Err(error)
+        }
+    };
+
+    match outer_scope {
+        Some(scope) => env.scope = scope,
+        None => ()
+    }
+
+    return result;
+}
+
+// Evaluates a form with the given local scope in place of the current one.
+pub fn eval_scoped(env: &mut Env, scope: &Scope, sx: &Sx) -> Result {
+    let outer_scope = mem::replace(&mut env.scope, scope.clone());
+    let result = eval(env, sx);
+    env.scope = outer_scope;
+    return result;
+}
+
+// Evaluates anything but a call.
+fn eval_value(env: &mut Env, sx: &Sx) -> Result {
     match sx {
-        Sx::Nil         |
-        Sx::Boolean(_)  |
-        Sx::Integer(_)  |
-        Sx::String(_)   |
-        Sx::Builtin(_)  |
-        Sx::Function(_) => {
+        Sx::Nil             |
+        Sx::Boolean(_)      |
+        Sx::Integer(_)      |
+        Sx::BigInteger(_)   |
+        Sx::Float(_)        |
+        Sx::Keyword(_)      |
+        Sx::Char(_)         |
+        Sx::String(_)       |
+        Sx::Tagged(_)       |
+        Sx::Builtin(_)      |
+        Sx::Function(_)     => {
             return Ok(sx.clone());
         },
 
@@ -19,7 +98,13 @@ pub fn eval(env: &mut Env, sx: &Sx) -> Result {
             return Ok(value.as_ref().clone());
         },
 
+        // Local bindings hide definitions, including those of core.
         Sx::Symbol(ref symbol) => {
+            match env.scope.lookup(symbol) {
+                Some(value) => return Ok(value),
+                None        => ()
+            }
+
             let mut effective_module = env.core_module.clone();
             let mut effective_symbol = symbol.clone();
 
@@ -49,32 +134,9 @@ pub fn eval(env: &mut Env, sx: &Sx) -> Result {
             }
         },
 
-        Sx::List(l) => {
-            match l.split_first() {
-                None => {
-                    return Ok(sx.clone());
-                },
-
-                Some((head, args)) => {
-                    match eval(env, head) {
-                        Ok(Sx::Builtin(builtin)) => {
-                            return apply_builtin(builtin, env, args);
-                        },
-
-                        Ok(Sx::Function(ref f)) => {
-                            return apply_function(f, env, args);
-                        },
-
-                        Ok(v) => {
-                            return Err(Error::NotAFunction(v.clone()));
-                        },
-
-                        error @ Err(_) => {
-                            return error;
-                        }
-                    }
-                }
-            }
+        // Only the empty list, since calls are applied by eval.
+        Sx::List(_) => {
+            return Ok(sx.clone());
         },
 
         Sx::Vector(v) => {
@@ -87,23 +149,62 @@ pub fn eval(env: &mut Env, sx: &Sx) -> Result {
             }
 
             return Ok(Sx::Vector(Arc::new(w)));
+        },
+
+        Sx::Map(m) => {
+            let mut n = im::OrdMap::new();
+            for (key, value) in m.iter() {
+                match (eval(env, key), eval(env, value)) {
+                    (Ok(key_result), Ok(value_result)) => n.insert(key_result, value_result),
+                    (error @ Err(_), _) => return error,
+                    (_, error @ Err(_)) => return error
+                };
+            }
+
+            return Ok(Sx::Map(Arc::new(n)));
+        },
+
+        Sx::Set(set) => {
+            let mut n = im::HashSet::new();
+            for sx in set.0.iter() {
+                match eval(env, sx) {
+                    Ok(result) => n.insert(result),
+                    error @ Err(_) => return error
+                };
+            }
+
+            return Ok(Sx::Set(SxSet(Arc::new(n))));
         }
     }
 }
 
-pub fn apply_builtin(builtin: &SxBuiltinInfo, env: &mut Env, arglist: &[Sx]) -> Result {
-    match builtin.callback {
-        SxBuiltinCallback::Special(special_fn) => {
-            return apply_special(builtin, special_fn, env, arglist);
+// Applies the value of the head of a call to its arguments.
+fn apply(env: &mut Env, head: &Sx, args: &[Sx]) -> TailResult {
+    match eval(env, head) {
+        Ok(Sx::Builtin(builtin)) => {
+            return apply_builtin_tail(builtin, env, args);
         },
 
-        SxBuiltinCallback::Primitive(primitive_fn) => {
-            return apply_primitive(builtin, primitive_fn, env, arglist);
+        Ok(Sx::Function(ref f)) => {
+            return apply_function_tail(f, env, args);
+        },
+
+        Ok(v) => {
+            return Err(Error::NotAFunction(v.clone()));
+        },
+
+        Err(error) => {
+            return Err(error);
         }
     }
 }
 
-fn apply_special(builtin: &SxBuiltinInfo, special_fn: SxBuiltinFn, env: &mut Env, args: &[Sx]) -> Result {
+pub fn apply_builtin(builtin: &SxBuiltinInfo, env: &mut Env, arglist: &[Sx]) -> Result {
+    let result = apply_builtin_tail(builtin, env, arglist);
+    return finish(env, result);
+}
+
+fn apply_builtin_tail(builtin: &SxBuiltinInfo, env: &mut Env, args: &[Sx]) -> TailResult {
     if args.len() < builtin.min_arity {
         return Err(Error::BuiltinTooFewArgs(builtin.name, builtin.min_arity, args.len()));
     }
@@ -116,34 +217,47 @@ fn apply_special(builtin: &SxBuiltinInfo, special_fn: SxBuiltinFn, env: &mut Env
         Some(_) | None => ()
     }
 
-    return special_fn(env, args);
-}
-
-fn apply_primitive(builtin: &SxBuiltinInfo, primitive_fn: SxBuiltinFn, env: &mut Env, args: &[Sx]) -> Result {
-    if args.len() < builtin.min_arity {
-        return Err(Error::BuiltinTooFewArgs(builtin.name, builtin.min_arity, args.len()));
-    }
+    match builtin.callback {
+        SxBuiltinCallback::Special(special_fn) => {
+            return special_fn(env, args).map(Tail::Value);
+        },
 
-    match builtin.max_arity {
-        Some(arity) if arity < args.len() => {
-            return Err(Error::BuiltinTooManyArgs(builtin.name, arity, args.len()));
+        SxBuiltinCallback::TailSpecial(tail_fn) => {
+            return tail_fn(env, args);
         },
 
-        Some(_) | None => ()
+        SxBuiltinCallback::Primitive(primitive_fn) => {
+            return apply_primitive(builtin, primitive_fn, env, args).map(Tail::Value);
+        }
     }
+}
 
+fn apply_primitive(builtin: &SxBuiltinInfo, primitive_fn: SxBuiltinFn, env: &mut Env, args: &[Sx]) -> Result {
     let mut result_args = args.to_vec();
-    for arg in result_args.iter_mut() {
+    for (index, arg) in result_args.iter_mut().enumerate() {
         match eval(env, arg) {
             Ok(result) => *arg = result,
             error @ Err(_) => return error
         }
+
+        match builtin.param(index) {
+            Some(param) if !param.ty.accepts(arg) => {
+                return Err(Error::BuiltinBadArg(builtin.name, index, param.ty, arg.clone()));
+            },
+
+            Some(_) | None => ()
+        }
     }
 
     return primitive_fn(env, &result_args);
 }
 
 pub fn apply_function(f: &SxFunction, env: &mut Env, args: &[Sx]) -> Result {
+    let result = apply_function_tail(f, env, args);
+    return finish(env, result);
+}
+
+fn apply_function_tail(f: &SxFunction, env: &mut Env, args: &[Sx]) -> TailResult {
     let arity = args.len();
     if arity < f.arity {
         return Err(Error::FnTooFewArgs(f.clone(), f.arity, arity));
@@ -153,25 +267,101 @@ pub fn apply_function(f: &SxFunction, env: &mut Env, args: &[Sx]) -> Result {
         return Err(Error::FnTooManyArgs(f.clone(), f.arity, arity));
     }
 
-    let mut sub_env = env.clone();
-    sub_env.current_module = f.module.clone();
-    for (binding, sx) in f.bindings.iter().zip(args.iter()) {
+    let mut values = Vec::new();
+    for sx in args.iter() {
         match eval(env, sx) {
-            Ok(ref result) => sub_env.define_current(binding, result),
-            error @ Err(_) => return error
+            Ok(result) => values.push(result),
+            Err(error) => return Err(error)
         }
     }
 
-    let exprs = f.body.clone();
-    let mut result = sx_nil!();
+    return enter_function(f, env, values);
+}
+
+// Binds the arguments of a call and evaluates all but the last expression
+// of the body, which is left to be evaluated in the environment of the call.
+fn enter_function(f: &SxFunction, env: &Env, values: Vec<Sx>) -> TailResult {
+    let arity = values.len();
+    if arity < f.arity {
+        return Err(Error::FnTooFewArgs(f.clone(), f.arity, arity));
+    }
+
+    if f.arity < arity {
+        return Err(Error::FnTooManyArgs(f.clone(), f.arity, arity));
+    }
+
+    let bindings = f.bindings
+        .iter()
+        .cloned()
+        .zip(values)
+        .collect::<Vec<_>>();
+
+    // The body sees the scope the function was created in, not the caller's.
+    let mut sub_env = env.clone();
+    sub_env.current_module = f.module.clone();
+    sub_env.scope = f.scope.extend(bindings);
+
+    let (last_expr, exprs) = match f.body.split_last() {
+        Some(split) => split,
+        None => return Ok(Tail::Value(sx_nil!()))
+    };
+
     for expr in exprs.iter() {
         match eval(&mut sub_env, expr) {
-            Ok(sub_result) => result = sub_result,
-            error @ Err(_) => return error
+            Ok(_) => (),
+            Err(error) => return Err(error)
         }
     }
 
-    return Ok(result);
+    return Ok(Tail::EvalIn(sub_env, last_expr.clone()));
+}
+
+// The macro a call refers to, if any. Local bindings and core symbols hide
+// macros of the same name, like they hide definitions.
+fn lookup_macro(env: &Env, symbol: &SxSymbol) -> Option<SxFunction> {
+    if env.scope.lookup(symbol).is_some() {
+        return None;
+    }
+
+    match module::entry_from_symbol(symbol)[..] {
+        [ref module, ref sub_symbol] if env.loaded_modules.contains(module) => {
+            return env.lookup_macro(module, sub_symbol).cloned();
+        },
+
+        [_] if env.lookup_core(symbol).is_none() => {
+            return env.lookup_macro(&env.current_module, symbol).cloned();
+        },
+
+        _ => return None
+    }
+}
+
+// Expands a macro call once, passing the unevaluated arguments to the macro,
+// or returns None for any other form.
+pub fn expand_macro(env: &mut Env, sx: &Sx) -> Option<Result> {
+    let l = match sx {
+        Sx::List(l) if !l.is_empty() => l,
+        _ => return None
+    };
+
+    let f = match l[0] {
+        Sx::Symbol(ref symbol) => lookup_macro(env, symbol)?,
+        _ => return None
+    };
+
+    let result = enter_function(&f, env, l[1 ..].to_vec());
+    return Some(finish(env, result));
+}
+
+// Evaluates what is left of a call made outside of the loop of eval.
+fn finish(env: &mut Env, result: TailResult) -> Result {
+    match result {
+        Ok(Tail::Value(value)) => return Ok(value),
+        Ok(Tail::Eval(sx)) => return eval(env, &sx),
+        Ok(Tail::EvalIn(mut sub_env, sx)) => return eval(&mut sub_env, &sx),
+        Ok(Tail::EvalScoped(scope, sx)) => return eval_scoped(env, &scope, &sx),
+        Err(error) => return Err(error)
+    }
 }
 
 // TODO: relocate primitive and special tests
@@ -181,6 +371,10 @@ mod tests {
 
     use std::sync::Arc;
 
+    use num_bigint::BigInt;
+    use num_traits;
+
+    use ::eval::scope::Scope;
     use ::read::read;
 
     fn mk_test_env() -> Env {
@@ -323,20 +517,46 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_special_fn_warnings() {
+        use ::eval::Warning;
+
+        let mut env = mk_test_env();
+        for sx in read(r#"
+            (def x 1)
+            (def f (fn (x y _z) (+ x 'y)))
+            (def g (fn (head w) (head (quote (1)))))
+            (def mk (fn () (fn (v) 1)))
+            (+ (mk) (mk))
+        "#).unwrap().iter() {
+            let _ = eval(&mut env, sx);
+        }
+
+        assert_eq!(vec![
+            Warning::ShadowDefinition(sx_symbol_unwrapped!("x"), sx_symbol_unwrapped!("test-eval")),
+            Warning::ShadowDefinition(sx_symbol_unwrapped!("head"), sx_symbol_unwrapped!("core")),
+            Warning::UnusedBinding(sx_symbol_unwrapped!("w")),
+            Warning::UnusedBinding(sx_symbol_unwrapped!("v"))
+        ], env.take_warnings());
+        assert!(env.take_warnings().is_empty());
+    }
+
     #[test]
     fn test_special_fn_too_few_args() {
         let f1 = Arc::new(SxFunctionInfo {
             module:     sx_symbol_unwrapped!("test-eval"),
             arity:      1,
             bindings:   vec![sx_symbol_unwrapped!("x")],
-            body:       Arc::new(vec![sx_symbol!("x")])
+            body:       Arc::new(vec![sx_symbol!("x")]),
+            scope:      Scope::new()
         });
 
         let f2 = Arc::new(SxFunctionInfo {
             module:     sx_symbol_unwrapped!("test-eval"),
             arity:      2,
             bindings:   vec![sx_symbol_unwrapped!("x"), sx_symbol_unwrapped!("y")],
-            body:       Arc::new(vec![sx_symbol!("x")])
+            body:       Arc::new(vec![sx_symbol!("x")]),
+            scope:      Scope::new()
         });
 
         test_eval_results(r#"
@@ -354,14 +574,16 @@ mod tests {
             module:     sx_symbol_unwrapped!("test-eval"),
             arity:      0,
             bindings:   vec![],
-            body:       Arc::new(vec![sx_nil!()])
+            body:       Arc::new(vec![sx_nil!()]),
+            scope:      Scope::new()
         });
 
         let f2 = Arc::new(SxFunctionInfo {
             module:     sx_symbol_unwrapped!("test-eval"),
             arity:      1,
             bindings:   vec![sx_symbol_unwrapped!("x")],
-            body:       Arc::new(vec![sx_symbol!("x")])
+            body:       Arc::new(vec![sx_symbol!("x")]),
+            scope:      Scope::new()
         });
 
         test_eval_results(r#"
@@ -451,6 +673,43 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn test_builtin_signatures() {
+        for builtin in ::eval::builtin::BUILTIN_TABLE.iter() {
+            match builtin.max_arity {
+                Some(max_arity) => assert_eq!(max_arity, builtin.params.len(), "{}", builtin.name),
+                None => assert!(builtin.min_arity < builtin.params.len(), "{}", builtin.name)
+            }
+        }
+
+        let signature = |name| ::eval::builtin::lookup(name).unwrap().signature();
+        assert_eq!("(env)", signature("env"));
+        assert_eq!("(get map key [default])", signature("get"));
+        assert_eq!("(fn bindings expr & exprs)", signature("fn"));
+        assert_eq!("(+ & xs)", signature("+"));
+
+        let param_name = |name, index| ::eval::builtin::lookup(name).unwrap().param(index).map(|param| param.name);
+        assert_eq!(None, param_name("env", 0));
+        assert_eq!(Some("default"), param_name("get", 2));
+        assert_eq!(None, param_name("get", 3));
+        assert_eq!(Some("xs"), param_name("+", 5));
+    }
+
+    #[test]
+    fn test_primitive_doc() {
+        test_eval_results(r#"
+            (doc get)
+            (doc fn)
+            (doc (fn (x y) (+ x y)))
+            (doc 1)
+        "#, vec![
+            Ok(sx_string!("(get map key [default])")),
+            Ok(sx_string!("(fn bindings expr & exprs)")),
+            Ok(sx_string!("(fn (x y) ...)")),
+            Err(Error::BuiltinBadArg("doc", 0, SxType::Function, sx_integer!(1)))
+        ]);
+    }
+
     #[test]
     fn test_primitive_error_apply() {
         test_eval_results(r#"
@@ -458,8 +717,8 @@ mod tests {
             (apply 1 '(1 2 3))
             (apply foo '(1 2 3))
         "#, vec![
-            Err(Error::BuiltinBadArg("apply", sx_boolean!(true))),
-            Err(Error::NotAFunction(sx_integer!(1))),
+            Err(Error::BuiltinBadArg("apply", 1, SxType::List, sx_boolean!(true))),
+            Err(Error::BuiltinBadArg("apply", 0, SxType::Function, sx_integer!(1))),
             Err(Error::Undefined(sx_symbol_unwrapped!("foo")))
         ]);
     }
@@ -533,7 +792,7 @@ mod tests {
         test_eval_results(r#"
             (+ 1 nil)
         "#, vec![
-            Err(Error::BuiltinBadArg("+", sx_nil!()))
+            Err(Error::BuiltinBadArg("+", 1, SxType::Number, sx_nil!()))
         ]);
     }
 
@@ -600,4 +859,741 @@ mod tests {
             [1 2 3 4]
         "#)
     }
+
+    #[test]
+    fn test_float_eval() {
+        test_eval(r#"
+            1.5
+            '-0.25
+            [inf (+ 1 1)]
+        "#, r#"
+            1.5
+            -0.25
+            [inf 2]
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_float_arithmetic() {
+        test_eval(r#"
+            (+ 1 1.5)
+            (+ 0.5 0.25 1)
+            (- 1.5)
+            (- 1 0.5 0.25)
+            (- 2.0 2)
+            (* 2 0.5)
+            (* 1.5 2 2)
+            (+ inf -inf)
+        "#, r#"
+            2.5
+            1.75
+            -1.5
+            0.25
+            0.0
+            1.0
+            6.0
+            nan
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_float_eq() {
+        test_eval(r#"
+            (= 1.5 1.5)
+            (= nan nan)
+            (= 0.0 -0.0)
+            (= 1 1.0)
+        "#, r#"
+            true
+            true
+            true
+            false
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_error_float_arithmetic() {
+        test_eval_results(r#"
+            (+ 1.5 nil)
+            (- "a" 1.5)
+            (- "a")
+            (* 2.0 true)
+        "#, vec![
+            Err(Error::BuiltinBadArg("+", 1, SxType::Number, sx_nil!())),
+            Err(Error::BuiltinBadArg("-", 0, SxType::Number, sx_string!("a"))),
+            Err(Error::BuiltinBadArg("-", 0, SxType::Number, sx_string!("a"))),
+            Err(Error::BuiltinBadArg("*", 1, SxType::Number, sx_boolean!(true)))
+        ]);
+    }
+
+    #[test]
+    fn test_primitive_big_integer_arithmetic() {
+        test_eval(r#"
+            (+ 9223372036854775807 1)
+            (- -9223372036854775808 1)
+            (- -9223372036854775808)
+            (* 4294967296 4294967296)
+            (* -4294967296 4294967296 4294967296)
+            (+ 100200300400500600700800 -100200300400500600700800)
+            (- (+ 9223372036854775807 1) 1)
+            (+ 100200300400500600700800 0.5)
+        "#, r#"
+            9223372036854775808
+            -9223372036854775809
+            9223372036854775808
+            18446744073709551616
+            -79228162514264337593543950336
+            0
+            9223372036854775807
+            1.002003004005006e23
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_big_integer_eq() {
+        test_eval(r#"
+            (= (* 4294967296 4294967296) 18446744073709551616)
+            (= (- (+ 9223372036854775807 1) 1) 9223372036854775807)
+            (= 18446744073709551616 18446744073709551617)
+        "#, r#"
+            true
+            true
+            false
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_big_integer_float_limit() {
+        let mut env = mk_test_env();
+        let big = Sx::from_big_integer(num_traits::pow(BigInt::from(10), 400));
+        let sum = sx_list![sx_symbol!("+"), big.clone(), sx_float!(1.0)];
+        let diff = sx_list![sx_symbol!("-"), sx_float!(1.0), big.clone()];
+
+        assert_eq!(Ok(sx_float!(f64::INFINITY)), eval(&mut env, &sum));
+        assert_eq!(Ok(sx_float!(f64::NEG_INFINITY)), eval(&mut env, &diff));
+    }
+
+    #[test]
+    fn test_map_eval() {
+        test_eval(r#"
+            (def foo 2)
+            {'foo foo (+ 1 2) [foo]}
+            {}
+        "#, r#"
+            foo
+            {3 [2] foo 2}
+            {}
+        "#)
+    }
+
+    #[test]
+    fn test_primitive_map() {
+        test_eval(r#"
+            (def m {'a 1 'b 2})
+            (get m 'a)
+            (get m 'c)
+            (get m 'c 3)
+            (assoc m 'c 3 'a 4)
+            (dissoc m 'a 'c)
+            (keys m)
+            (vals m)
+            (contains? m 'b)
+            (contains? m 'c)
+            (= m (assoc {} 'b 2 'a 1))
+        "#, r#"
+            m
+            1
+            nil
+            3
+            {a 4 b 2 c 3}
+            {b 2}
+            [a b]
+            [1 2]
+            true
+            false
+            true
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_error_map() {
+        test_eval_results(r#"
+            (get '(1 2) 1)
+            (assoc {} 'a)
+            (assoc {} 'a 1 'b)
+            (keys [1 2])
+        "#, vec![
+            Err(Error::BuiltinBadArg("get", 0, SxType::Map, sx_list![sx_integer!(1), sx_integer!(2)])),
+            Err(Error::BuiltinTooFewArgs("assoc", 3, 2)),
+            Err(Error::BuiltinUnpairedKey("assoc", sx_symbol!("b"))),
+            Err(Error::BuiltinBadArg("keys", 0, SxType::Map, sx_vector![sx_integer!(1), sx_integer!(2)]))
+        ]);
+    }
+
+    #[test]
+    fn test_set_eval() {
+        test_eval(r#"
+            (def foo 2)
+            #{'foo foo (+ 1 2)}
+            #{1 (- 3 2)}
+        "#, r#"
+            foo
+            #{2 3 foo}
+            #{1}
+        "#)
+    }
+
+    #[test]
+    fn test_primitive_set() {
+        test_eval(r#"
+            (def s #{1 2 3})
+            (conj s 3 4)
+            (disj s 1 5)
+            (union s #{3 4} #{5})
+            (intersection s #{2 3 4} #{3 4})
+            (difference s #{1 4} #{2})
+            (subset? #{1 2} s)
+            (subset? s #{1 2})
+            (= s (conj #{} 3 2 1))
+        "#, r#"
+            s
+            #{1 2 3 4}
+            #{2 3}
+            #{1 2 3 4 5}
+            #{3}
+            #{3}
+            true
+            false
+            true
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_error_set() {
+        test_eval_results(r#"
+            (conj [1] 2)
+            (union #{1} [2])
+            (subset? #{1} '(1))
+            (subset? nil #{1})
+        "#, vec![
+            Err(Error::BuiltinBadArg("conj", 0, SxType::Set, sx_vector![sx_integer!(1)])),
+            Err(Error::BuiltinBadArg("union", 1, SxType::Set, sx_vector![sx_integer!(2)])),
+            Err(Error::BuiltinBadArg("subset?", 1, SxType::Set, sx_list![sx_integer!(1)])),
+            Err(Error::BuiltinBadArg("subset?", 0, SxType::Set, sx_nil!()))
+        ]);
+    }
+
+    #[test]
+    fn test_keyword_eval() {
+        test_eval(r#"
+            :foo
+            (def m {:a 1 :b [:c]})
+            (get m :b)
+            (= :a (head '(:a)))
+            (= :a 'a)
+        "#, r#"
+            :foo
+            m
+            [:c]
+            true
+            false
+        "#);
+    }
+
+    #[test]
+    fn test_special_quasiquote() {
+        test_eval(r#"
+            (def x 1)
+            (def xs '(2 3))
+            `(a ,x ,@xs [,x ,@xs] b)
+            `[,@[] ,@() ,@nil]
+            `(a `(b ,(c ,x)))
+            `(a `(b ,@,xs))
+            `'(a ,x)
+            `,x
+        "#, r#"
+            x
+            xs
+            (a 1 2 3 [1 2 3] b)
+            []
+            (a (quasiquote (b (unquote (c 1)))))
+            (a (quasiquote (b (unquote-splicing (2 3)))))
+            '(a 1)
+            1
+        "#);
+    }
+
+    #[test]
+    fn test_special_error_quasiquote() {
+        test_eval_results(r#"
+            ,x
+            (unquote-splicing '(1 2))
+            `(a ,@1)
+            `,@'(1)
+            `(a ,y)
+        "#, vec![
+            Err(Error::UnquoteOutsideQuasiquote(sx_list![sx_symbol!("unquote"), sx_symbol!("x")])),
+            Err(Error::UnquoteOutsideQuasiquote(sx_list![
+                sx_symbol!("unquote-splicing"),
+                sx_quote!(sx_list![sx_integer!(1), sx_integer!(2)])
+            ])),
+            Err(Error::BuiltinBadArg("unquote-splicing", 0, SxType::Sequence, sx_integer!(1))),
+            Err(Error::SpliceOutsideSequence(sx_list![
+                sx_symbol!("unquote-splicing"),
+                sx_quote!(sx_list![sx_integer!(1)])
+            ])),
+            Err(Error::Undefined(sx_symbol_unwrapped!("y")))
+        ]);
+    }
+
+    #[test]
+    fn test_unicode_symbols() {
+        test_eval(r#"
+            (def MyValue 1)
+            (def λ (fn (x_1) (+ x_1 MyValue)))
+            (λ 2)
+        "#, r#"
+            MyValue
+            λ
+            3
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_chars() {
+        test_eval(r#"
+            \a
+            (char->int \a)
+            (char->int \u{1F600})
+            (int->char 955)
+            (string->chars "ae\u{301}北")
+            (chars->string [\a \space \é])
+            (chars->string (string->chars "北京市"))
+            (chars->string ())
+        "#, r#"
+            \a
+            97
+            128512
+            \λ
+            [\a \é \北]
+            "a é"
+            "北京市"
+            ""
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_error_chars() {
+        test_eval_results(r#"
+            (char->int "a")
+            (char->int \é)
+            (int->char -1)
+            (int->char 55296)
+            (string->chars \a)
+            (chars->string [\a "b"])
+        "#, vec![
+            Err(Error::BuiltinBadArg("char->int", 0, SxType::ScalarChar, sx_string!("a"))),
+            Err(Error::BuiltinBadArg("char->int", 0, SxType::ScalarChar, sx_char!("e\u{301}"))),
+            Err(Error::BuiltinBadArg("int->char", 0, SxType::ScalarValue, sx_integer!(-1))),
+            Err(Error::BuiltinBadArg("int->char", 0, SxType::ScalarValue, sx_integer!(55296))),
+            Err(Error::BuiltinBadArg("string->chars", 0, SxType::String, sx_char!("a"))),
+            Err(Error::BuiltinBadArg("chars->string", 0, SxType::CharSequence, sx_vector![sx_char!("a"), sx_string!("b")]))
+        ]);
+    }
+
+    #[test]
+    fn test_primitive_tagged() {
+        test_eval(r#"
+            #inst "2018-11-05T13:15:30Z"
+            (tag #uuid "F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6")
+            (untag #inst "2018-11-05")
+            (untag (tagged 'point [1 (+ 1 1)]))
+            (= (tagged 'inst "2018-11-05") #inst "2018-11-05")
+        "#, r#"
+            #inst "2018-11-05T13:15:30Z"
+            uuid
+            "2018-11-05"
+            [1 2]
+            true
+        "#);
+    }
+
+    #[test]
+    fn test_primitive_error_tagged() {
+        test_eval_results(r#"
+            (tagged "point" 1)
+            (tag 1)
+            (untag "2018-11-05")
+        "#, vec![
+            Err(Error::BuiltinBadArg("tagged", 0, SxType::Symbol, sx_string!("point"))),
+            Err(Error::BuiltinBadArg("tag", 0, SxType::Tagged, sx_integer!(1))),
+            Err(Error::BuiltinBadArg("untag", 0, SxType::Tagged, sx_string!("2018-11-05")))
+        ]);
+    }
+
+    #[test]
+    fn test_primitive_error_range() {
+        let big = Sx::BigInteger(Arc::new(BigInt::parse_bytes(b"100000000000000000000", 10).unwrap()));
+
+        test_eval_results(r#"
+            (range 100000000000000000000)
+            (range 1 100000000000000000000)
+            (range "1")
+            (range 1 "2")
+        "#, vec![
+            Err(Error::BuiltinArgOutOfRange("range", 0, big.clone())),
+            Err(Error::BuiltinArgOutOfRange("range", 1, big.clone())),
+            Err(Error::BuiltinBadArg("range", 0, SxType::Integer, sx_string!("1"))),
+            Err(Error::BuiltinBadArg("range", 1, SxType::Integer, sx_string!("2")))
+        ]);
+
+        assert_eq!("argument 2 to range is out of range, got integer 100000000000000000000",
+                   Error::BuiltinArgOutOfRange("range", 1, big).to_string());
+    }
+
+    #[test]
+    fn test_special_defreader() {
+        use ::read::{read_with, Error as ReadError};
+
+        let mut env = mk_test_env();
+        for sx in read("(def origin 0) (defreader point (fn (v) (tagged 'point (cons origin v))))").unwrap().iter() {
+            eval(&mut env, sx).expect("eval error");
+        }
+
+        let sxs = read_with("#point (1 2) [#point ()]", &env.dispatch_table()).expect("read error");
+        assert_eq!(vec!["#point (0 1 2)", "[#point (0)]"],
+                   sxs.iter().map(|sx| eval(&mut env, sx).unwrap().to_string()).collect::<Vec<_>>());
+
+        match read_with("#point 1", &env.dispatch_table()) {
+            Err(ref errors) => match errors[..] {
+                [ReadError::InvalidTaggedLiteral(ref tag, _, _)] => assert_eq!("point", tag),
+                _ => panic!("unexpected errors: {:?}", errors)
+            },
+
+            Ok(sxs) => panic!("unexpected read: {:?}", sxs)
+        }
+
+        assert!(read("#point (1 2)").is_err());
+
+        // Handlers see definitions made after them.
+        for sx in read("(defreader twice (fn (v) (double v))) (def double (fn (v) (* 2 v)))").unwrap().iter() {
+            eval(&mut env, sx).expect("eval error");
+        }
+
+        assert_eq!(Ok(vec![sx_integer!(42)]), read_with("#twice 21", &env.dispatch_table()));
+
+        assert_eq!(Err(Error::NotAFunction(sx_integer!(1))), eval(&mut env, &read("(defreader one 1)").unwrap()[0]));
+        assert_eq!(Err(Error::BuiltinBadArg("defreader", 0, SxType::Symbol, sx_string!("one"))),
+                   eval(&mut env, &read("(defreader \"one\" head)").unwrap()[0]));
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        test_eval(r#"
+            (def count-down (fn (n) (if (= n 0) 'done (count-down (- n 1)))))
+            (count-down 1000000)
+            (def even? (fn (n) (if (= n 0) true (odd? (- n 1)))))
+            (def odd? (fn (n) (if (= n 0) false (even? (- n 1)))))
+            (even? 100001)
+        "#, r#"
+            count-down
+            done
+            even?
+            odd?
+            false
+        "#);
+    }
+
+    #[test]
+    fn test_closures() {
+        test_eval(r#"
+            (def adder (fn (x) (fn (y) (+ x y))))
+            (def add-2 (adder 2))
+            (add-2 3)
+            ((adder 10) 5)
+            (def curry (fn (f x) (fn (y) (f x y))))
+            ((curry cons 1) '(2 3))
+            (def apply-twice (fn (f x) (f (f x))))
+            (apply-twice add-2 1)
+            (def shadow (fn (x) ((fn (x) x) (+ x 1))))
+            (shadow 1)
+        "#, r#"
+            adder
+            add-2
+            5
+            15
+            curry
+            (1 2 3)
+            apply-twice
+            5
+            shadow
+            2
+        "#);
+    }
+
+    #[test]
+    fn test_closures_shadow_core() {
+        test_eval(r#"
+            ((fn (head) head) 1)
+            (let ((get 2) (keys 3)) (+ get keys))
+            (def wrap (fn (doc) (fn () doc)))
+            ((wrap 4))
+        "#, r#"
+            1
+            5
+            wrap
+            4
+        "#);
+    }
+
+    #[test]
+    fn test_closures_not_dynamic() {
+        test_eval_results(r#"
+            (def get-x (fn () x))
+            ((fn (x) (get-x)) 1)
+        "#, vec![
+            Ok(sx_symbol!("get-x")),
+            Err(Error::Undefined(sx_symbol_unwrapped!("x")))
+        ]);
+    }
+
+    #[test]
+    fn test_special_let() {
+        test_eval(r#"
+            (def x 1)
+            (let ((x 2) (y x)) (+ x y))
+            (let* ((x 2) (y x)) (+ x y))
+            (let* ((x 2) (x (+ x 1))) x)
+            (let ((f (fn (n) (+ n x)))) (let ((x 10)) (f 1)))
+            (letrec ((even? (fn (n) (if (= n 0) true (odd? (- n 1)))))
+                     (odd? (fn (n) (if (= n 0) false (even? (- n 1))))))
+              (even? 100000))
+            ((letrec ((count-down (fn (n) (if (= n 0) 'done (count-down (- n 1)))))) count-down) 3)
+            (def mk (fn (z) (letrec ((f (fn () (+ z (g)))) (g (fn () 1))) f)))
+            ((mk 5))
+            (let () 1 2)
+        "#, r#"
+            x
+            3
+            4
+            3
+            2
+            true
+            done
+            mk
+            6
+            2
+        "#);
+    }
+
+    #[test]
+    fn test_special_let_definitions() {
+        test_eval(r#"
+            (let ((a 1)) (def x a))
+            x
+            (let* ((a 2)) (def y a) (def z (+ y 1)))
+            (+ y z)
+            (letrec ((f (fn () 4))) (defmacro four () (f)))
+            (four)
+            (let ((a 5)) a)
+        "#, r#"
+            x
+            1
+            z
+            5
+            four
+            4
+            5
+        "#);
+    }
+
+    #[test]
+    fn test_special_let_errors() {
+        test_eval_results(r#"
+            (let (x) x)
+            (let ((1 2)) 1)
+            (let ((x 1 2)) x)
+            (let ((x 1) (x 2)) x)
+            (letrec ((x 1) (x 2)) x)
+            (let x x)
+            (letrec ((x 1) (y x)) y)
+            (letrec ((f (fn () 1)) (y (f))) y)
+            (let ((fn head)) (letrec ((f (fn () 1))) f))
+            (do (let ((w 1)) w) w)
+        "#, vec![
+            Err(Error::InvalidBinding(sx_symbol!("x"))),
+            Err(Error::InvalidBinding(sx_list![sx_integer!(1), sx_integer!(2)])),
+            Err(Error::InvalidBinding(sx_list![sx_symbol!("x"), sx_integer!(1), sx_integer!(2)])),
+            Err(Error::DuplicateBinding(sx_symbol_unwrapped!("x"))),
+            Err(Error::DuplicateBinding(sx_symbol_unwrapped!("x"))),
+            Err(Error::BuiltinBadArg("let", 0, SxType::List, sx_symbol!("x"))),
+            Err(Error::RecursiveBindingNotFn(sx_symbol_unwrapped!("x"), sx_integer!(1))),
+            Err(Error::RecursiveBindingNotFn(sx_symbol_unwrapped!("y"), sx_list![sx_symbol!("f")])),
+            Err(Error::RecursiveBindingNotFn(sx_symbol_unwrapped!("f"), sx_list![sx_symbol!("fn"), sx_list![], sx_integer!(1)])),
+            Err(Error::Undefined(sx_symbol_unwrapped!("w")))
+        ]);
+    }
+
+    #[test]
+    fn test_special_let_warnings() {
+        use ::eval::Warning;
+
+        let mut env = mk_test_env();
+        for sx in read(r#"
+            (let ((head 1) (unused 2)) head)
+            (let* ((a 1) (b a)) b)
+            (letrec ((f (fn () (g))) (g (fn () (f)))) nil)
+        "#).unwrap().iter() {
+            let _ = eval(&mut env, sx);
+        }
+
+        assert_eq!(vec![
+            Warning::ShadowDefinition(sx_symbol_unwrapped!("head"), sx_symbol_unwrapped!("core")),
+            Warning::UnusedBinding(sx_symbol_unwrapped!("unused"))
+        ], env.take_warnings());
+    }
+
+    #[test]
+    fn test_special_control_flow() {
+        test_eval(r#"
+            (do)
+            (do 1 2 3)
+            (cond)
+            (cond (false 1) (nil 2) (true 3) (true 4))
+            (cond (false 1) (else 2 3))
+            (cond (false 1) ('(4)))
+            (cond (false 1))
+            (when nil (undefined))
+            (when 1 2 3)
+            (unless false 2 3)
+            (unless 1 (undefined))
+            (and)
+            (and 1 nil (undefined))
+            (and 1 2 3)
+            (or)
+            (or nil false 3 (undefined))
+            (or nil false)
+        "#, r#"
+            nil
+            3
+            nil
+            3
+            3
+            (4)
+            nil
+            nil
+            3
+            3
+            nil
+            true
+            nil
+            3
+            nil
+            3
+            false
+        "#);
+    }
+
+    #[test]
+    fn test_special_control_flow_errors() {
+        test_eval_results(r#"
+            (cond (false 1) ())
+            (cond 1)
+            (when)
+            (do (undefined) 1)
+        "#, vec![
+            Err(Error::BuiltinBadArg("cond", 1, SxType::NonEmptyList, sx_list![])),
+            Err(Error::BuiltinBadArg("cond", 0, SxType::NonEmptyList, sx_integer!(1))),
+            Err(Error::BuiltinTooFewArgs("when", 1, 0)),
+            Err(Error::Undefined(sx_symbol_unwrapped!("undefined")))
+        ]);
+    }
+
+    #[test]
+    fn test_special_control_flow_tail_calls() {
+        test_eval(r#"
+            (def loop (fn (n)
+              (cond ((= n 0) 'done)
+                    (else (do (when false 1) (and true (or nil (loop (- n 1)))))))))
+            (loop 100000)
+            (def loop-let (fn (n) (unless (= n 0) (let* ((m (- n 1))) (loop-let m)))))
+            (loop-let 100000)
+        "#, r#"
+            loop
+            done
+            loop-let
+            nil
+        "#);
+    }
+
+    #[test]
+    fn test_special_defmacro() {
+        test_eval(r#"
+            (defmacro swap (x y) `(,y ,x))
+            (swap '(1 2) head)
+            (defmacro my-unless (c x y) `(if ,c ,y ,x))
+            (my-unless false 1 (undefined))
+            (defmacro my-or (x y) (let ((tmp (gensym))) `(let ((,tmp ,x)) (if ,tmp ,tmp ,y))))
+            (let ((tmp 1)) (my-or nil tmp))
+            (macroexpand-1 '(my-unless a (swap b c) d))
+            (defmacro not-nil (x) `(my-unless (= ,x nil) true false))
+            (macroexpand '(not-nil a))
+            (macroexpand '(head (a b)))
+            (def count-down (fn (n) (my-unless (= n 0) (count-down (- n 1)) 'done)))
+            (count-down 100000)
+            ((fn (swap) (swap '(1))) head)
+        "#, r#"
+            swap
+            1
+            my-unless
+            1
+            my-or
+            1
+            (if a d (swap b c))
+            not-nil
+            (if (= a nil) false true)
+            (head (a b))
+            count-down
+            done
+            1
+        "#);
+    }
+
+    #[test]
+    fn test_special_defmacro_errors() {
+        test_eval_results(r#"
+            (defmacro swap (x y) `(,y ,x))
+            (defmacro swap (x) x)
+            (def swap 1)
+            (defmacro head (x) x)
+            (defmacro "m" (x) x)
+            (defmacro m x x)
+        "#, vec![
+            Ok(sx_symbol!("swap")),
+            Err(Error::Redefine(sx_symbol_unwrapped!("swap"))),
+            Err(Error::Redefine(sx_symbol_unwrapped!("swap"))),
+            Err(Error::RedefineCore(sx_symbol_unwrapped!("head"))),
+            Err(Error::DefineBadSymbol(sx_string!("m"))),
+            Err(Error::BuiltinBadArg("defmacro", 1, SxType::List, sx_symbol!("x")))
+        ]);
+
+        let mut env = mk_test_env();
+        eval(&mut env, &read("(defmacro swap (x y) `(,y ,x))").unwrap()[0]).expect("eval error");
+
+        let swap = env.lookup_macro(&env.current_module, &sx_symbol_unwrapped!("swap")).unwrap().clone();
+        assert_eq!(Err(Error::FnTooFewArgs(swap, 2, 1)), eval(&mut env, &read("(swap 1)").unwrap()[0]));
+    }
+
+    #[test]
+    fn test_primitive_gensym() {
+        test_eval(r#"
+            (gensym)
+            (gensym "tmp")
+            (= (gensym) (gensym))
+        "#, r#"
+            g__1
+            tmp__2
+            false
+        "#);
+    }
 }
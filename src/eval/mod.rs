@@ -1,15 +1,34 @@
 pub mod env;
 pub mod eval;
 pub mod module;
+pub mod scope;
 
 mod builtin;
 
 use std::result;
 
+use ::diagnostic::{Diagnostic, Source};
+use ::eval::env::Env;
+use ::eval::scope::Scope;
 use ::read;
-use ::sx::{Sx, SxSymbol, SxFunction};
+use ::read::Form;
+use ::sx::{Sx, SxSymbol, SxFunction, SxType};
 
 pub type Result = result::Result<Sx, Error>;
+pub type TailResult = result::Result<Tail, Error>;
+
+// What is left of an evaluation after a step, so that forms in tail position
+// are evaluated by the caller's loop rather than by recursion.
+pub enum Tail {
+    Value(Sx),
+    // A form to evaluate in the current environment.
+    Eval(Sx),
+    // A form to evaluate in a new environment, such as a function body.
+    EvalIn(Env, Sx),
+    // A form to evaluate in the current environment with a new local scope,
+    // such as a let body, so that definitions made by it are kept.
+    EvalScoped(Scope, Sx)
+}
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum Error {
@@ -22,10 +41,19 @@ pub enum Error {
     NotAFunction(Sx),
     InvalidBinding(Sx),
     DuplicateBinding(SxSymbol),
-    // TODO: top-level shadow error
-
-    // TODO: BadArg expected info
-    BuiltinBadArg(&'static str, Sx),
+    // The name bound by letrec and the form of its value.
+    RecursiveBindingNotFn(SxSymbol, Sx),
+    UnquoteOutsideQuasiquote(Sx),
+    SpliceOutsideSequence(Sx),
+
+    // The builtin name, the argument index from zero, the type expected
+    // of the argument and the argument.
+    BuiltinBadArg(&'static str, usize, SxType, Sx),
+    // An argument of the right type beyond what the builtin supports, such
+    // as a big integer bound of a range.
+    BuiltinArgOutOfRange(&'static str, usize, Sx),
+    // The builtin name and the last key, which has no value.
+    BuiltinUnpairedKey(&'static str, Sx),
     BuiltinTooFewArgs(&'static str, usize, usize),
     BuiltinTooManyArgs(&'static str, usize, usize),
 
@@ -38,11 +66,278 @@ pub enum Error {
     ModuleMultipleOptions(SxSymbol, Vec<String>),
     ModuleIoOpenError(SxSymbol, String),
     ModuleIoReadError(SxSymbol, String),
-    ModuleReadErrors(SxSymbol, Vec<read::Error>),
-    ModuleEvalErrors(SxSymbol, Vec<Error>),
+    ModuleReadErrors(SxSymbol, Source, Vec<read::Error>),
+    // Each error along with the top-level form it was raised from.
+    ModuleEvalErrors(SxSymbol, Source, Vec<(Error, Form)>),
     ModuleNotLoaded(SxSymbol)
 }
 
+// Problems that do not stop evaluation, reported through the warnings of
+// the environment.
+#[derive(Eq, PartialEq, Debug)]
+pub enum Warning {
+    // The function parameter or let binding, and the module whose definition
+    // it shadows.
+    ShadowDefinition(SxSymbol, SxSymbol),
+    UnusedBinding(SxSymbol),
+    // Each warning along with the top-level form it was raised from.
+    ModuleWarnings(SxSymbol, Source, Vec<(Warning, Form)>)
+}
+
+impl Warning {
+    // Stable codes, so that new warnings get new codes. W0002 was used for
+    // parameters hidden by core symbols, which local bindings now shadow.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Warning::ShadowDefinition(_, _)     => "W0001",
+            Warning::UnusedBinding(_)           => "W0003",
+            Warning::ModuleWarnings(_, _, _)    => "W0004"
+        }
+    }
+
+    // Describes the warning raised while evaluating the given top-level form.
+    pub fn diagnostic(&self, form: Option<&Form>) -> Diagnostic {
+        let (culprit, label, diagnostic) = match self {
+            Warning::ShadowDefinition(symbol, module_name) => {
+                let message = format!("binding {} shadows the definition of {} in module {}", symbol, symbol, module_name);
+                let diagnostic = Diagnostic::warning(self.code(), &message)
+                    .with_help("rename the binding");
+                (Some(symbol), "shadows a definition", diagnostic)
+            },
+
+            Warning::UnusedBinding(symbol) => {
+                let message = format!("unused binding {}", symbol);
+                let diagnostic = Diagnostic::warning(self.code(), &message)
+                    .with_help(&format!("prefix it with an underscore if this is intended: _{}", symbol));
+                (Some(symbol), "never used", diagnostic)
+            },
+
+            Warning::ModuleWarnings(module_name, source, warnings) => {
+                let children = warnings
+                    .iter()
+                    .map(|(warning, form)| warning.diagnostic(Some(form)).with_source(source))
+                    .collect();
+
+                let diagnostic = Diagnostic::warning(self.code(), &format!("warnings in module {}", module_name))
+                    .with_children(children);
+                (None, "", diagnostic)
+            }
+        };
+
+        let form = match form {
+            Some(form) => form,
+            None => return diagnostic
+        };
+
+        // Functions created by a call are not found in the calling form.
+        match culprit.and_then(|symbol| locate(form, &Sx::Symbol(symbol.clone()))) {
+            Some(culprit) => return diagnostic.with_primary(culprit.span, label),
+            None if culprit.is_some() => return diagnostic.with_primary(form.span, "raised while evaluating this"),
+            None => return diagnostic.with_primary(form.span, label)
+        }
+    }
+}
+
+impl Error {
+    // Stable codes, so that new errors get new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Undefined(_)                 => "E0001",
+            Error::Redefine(_)                  => "E0002",
+            Error::RedefineCore(_)              => "E0003",
+            Error::DefineBadSymbol(_)           => "E0004",
+            Error::SymbolBadModuleFormat(_)     => "E0005",
+            Error::NotAFunction(_)              => "E0006",
+            Error::InvalidBinding(_)            => "E0007",
+            Error::DuplicateBinding(_)          => "E0008",
+            Error::UnquoteOutsideQuasiquote(_)  => "E0009",
+            Error::BuiltinBadArg(_, _, _, _)    => "E0010",
+            Error::BuiltinTooFewArgs(_, _, _)   => "E0011",
+            Error::BuiltinTooManyArgs(_, _, _)  => "E0012",
+            Error::FnTooFewArgs(_, _, _)        => "E0013",
+            Error::FnTooManyArgs(_, _, _)       => "E0014",
+            Error::ModuleSelfRefer(_)           => "E0015",
+            Error::ModulePathError(_, _)        => "E0016",
+            Error::ModuleNotFound(_, _)         => "E0017",
+            Error::ModuleMultipleOptions(_, _)  => "E0018",
+            Error::ModuleIoOpenError(_, _)      => "E0019",
+            Error::ModuleIoReadError(_, _)      => "E0020",
+            Error::ModuleReadErrors(_, _, _)    => "E0021",
+            Error::ModuleEvalErrors(_, _, _)    => "E0022",
+            Error::ModuleNotLoaded(_)           => "E0023",
+            Error::SpliceOutsideSequence(_)     => "E0024",
+            Error::BuiltinArgOutOfRange(_, _, _) => "E0025",
+            Error::BuiltinUnpairedKey(_, _)     => "E0026",
+            Error::RecursiveBindingNotFn(_, _)  => "E0027"
+        }
+    }
+
+    // Describes the error raised while evaluating the given top-level form,
+    // pointing at the part of it that caused the error when it can be found.
+    pub fn diagnostic(&self, form: Option<&Form>) -> Diagnostic {
+        let diagnostic = match self {
+            Error::ModuleReadErrors(module_name, source, read_errors) => {
+                let children = read_errors
+                    .iter()
+                    .map(|read_error| read_error.diagnostic().with_source(source))
+                    .collect();
+
+                Diagnostic::error(self.code(), &format!("failed to read module {}", module_name))
+                    .with_children(children)
+            },
+
+            Error::ModuleEvalErrors(module_name, source, eval_errors) => {
+                let children = eval_errors
+                    .iter()
+                    .map(|(eval_error, form)| eval_error.diagnostic(Some(form)).with_source(source))
+                    .collect();
+
+                Diagnostic::error(self.code(), &format!("failed to evaluate module {}", module_name))
+                    .with_children(children)
+            },
+
+            Error::ModuleNotFound(_, module_paths) => {
+                module_paths
+                    .iter()
+                    .fold(Diagnostic::error(self.code(), &self.to_string()),
+                          |diagnostic, module_path| diagnostic.with_note(&format!("searched {}", module_path)))
+            },
+
+            Error::Undefined(_) => {
+                Diagnostic::error(self.code(), &self.to_string())
+                    .with_help("define it with def before using it")
+            },
+
+            Error::RedefineCore(_) => {
+                Diagnostic::error(self.code(), &self.to_string())
+                    .with_note("core symbols are visible in every module")
+            },
+
+            Error::RecursiveBindingNotFn(_, _) => {
+                Diagnostic::error(self.code(), &self.to_string())
+                    .with_help("bind it with let or let* instead")
+            },
+
+            _ => Diagnostic::error(self.code(), &self.to_string())
+        };
+
+        let diagnostic = match self {
+            Error::BuiltinBadArg(name, _, _, _)     |
+            Error::BuiltinArgOutOfRange(name, _, _) |
+            Error::BuiltinUnpairedKey(name, _)      |
+            Error::BuiltinTooFewArgs(name, _, _)    |
+            Error::BuiltinTooManyArgs(name, _, _)   => {
+                match builtin::lookup(name) {
+                    Some(builtin) => diagnostic.with_note(&format!("signature: {}", builtin.signature())),
+                    None => diagnostic
+                }
+            },
+
+            _ => diagnostic
+        };
+
+        let form = match form {
+            Some(form) => form,
+            None => return diagnostic
+        };
+
+        let label = match self {
+            Error::Undefined(_)                 => "not defined".to_string(),
+            Error::Redefine(_)                  => "already defined".to_string(),
+            Error::RedefineCore(_)              => "defined in core".to_string(),
+            Error::DefineBadSymbol(_)           => "expected a symbol".to_string(),
+            Error::NotAFunction(_)              => "not a function".to_string(),
+            Error::InvalidBinding(_)            => "invalid binding".to_string(),
+            Error::DuplicateBinding(_)          => "bound more than once".to_string(),
+            Error::RecursiveBindingNotFn(_, _)  => "not a fn form".to_string(),
+            Error::SpliceOutsideSequence(_)     => "not inside a list or vector".to_string(),
+            Error::BuiltinBadArg(_, _, ty, _)   => format!("expected {}", ty.description()),
+            Error::BuiltinArgOutOfRange(_, _, _) => "out of range".to_string(),
+            Error::BuiltinUnpairedKey(_, _)     => "missing a value".to_string(),
+            Error::BuiltinTooFewArgs(_, _, _)   |
+            Error::FnTooFewArgs(_, _, _)        => "too few arguments".to_string(),
+            Error::BuiltinTooManyArgs(_, _, _)  |
+            Error::FnTooManyArgs(_, _, _)       => "too many arguments".to_string(),
+            _                                   => String::new()
+        };
+
+        let culprit = match (self, self.culprit()) {
+            (Error::BuiltinBadArg(name, index, _, _), Some(ref sx))     |
+            (Error::BuiltinArgOutOfRange(name, index, _), Some(ref sx)) => {
+                locate_argument(form, name, *index, sx).or_else(|| locate(form, sx)).unwrap_or(form)
+            },
+
+            (_, Some(ref sx)) => locate(form, sx).unwrap_or(form),
+            (_, None) => form
+        };
+
+        return diagnostic.with_primary(culprit.span, &label);
+    }
+
+    // The value that caused the error, to be looked up in the source form.
+    fn culprit(&self) -> Option<Sx> {
+        match self {
+            Error::Undefined(symbol)                |
+            Error::Redefine(symbol)                 |
+            Error::RedefineCore(symbol)             |
+            Error::SymbolBadModuleFormat(symbol)    |
+            Error::DuplicateBinding(symbol)         => return Some(Sx::Symbol(symbol.clone())),
+            Error::DefineBadSymbol(sx)              |
+            Error::NotAFunction(sx)                 |
+            Error::InvalidBinding(sx)               |
+            Error::RecursiveBindingNotFn(_, sx)     |
+            Error::UnquoteOutsideQuasiquote(sx)     |
+            Error::SpliceOutsideSequence(sx)        |
+            Error::BuiltinBadArg(_, _, _, sx)       |
+            Error::BuiltinArgOutOfRange(_, _, sx)   |
+            Error::BuiltinUnpairedKey(_, sx)        => return Some(sx.clone()),
+            _                                       => return None
+        }
+    }
+}
+
+// Finds the first form read as the given value, searching depth first.
+fn locate<'a>(form: &'a Form, sx: &Sx) -> Option<&'a Form> {
+    if form.sx == *sx {
+        return Some(form);
+    }
+
+    return form.children.iter().filter_map(|child| locate(child, sx)).next();
+}
+
+// Finds the argument at the given index in a call to the named builtin,
+// preferring a call where that argument was read as the given value.
+fn locate_argument<'a>(form: &'a Form, name: &str, index: usize, sx: &Sx) -> Option<&'a Form> {
+    let mut arguments = vec![];
+    calls_arguments(form, name, index, &mut arguments);
+
+    return arguments
+        .iter()
+        .find(|argument| argument.sx == *sx)
+        .or_else(|| arguments.first())
+        .cloned();
+}
+
+fn calls_arguments<'a>(form: &'a Form, name: &str, index: usize, arguments: &mut Vec<&'a Form>) {
+    let is_call = match form.sx {
+        Sx::List(_) => match form.children.first().map(|head| &head.sx) {
+            Some(Sx::Symbol(symbol)) => symbol.as_str() == name || symbol.ends_with(&format!("/{}", name)),
+            _ => false
+        },
+        _ => false
+    };
+
+    if is_call {
+        if let Some(argument) = form.children.get(index + 1) {
+            arguments.push(argument);
+        }
+    }
+
+    for child in form.children.iter() {
+        calls_arguments(child, name, index, arguments);
+    }
+}
+
 impl ToString for Error {
     fn to_string(&self) -> String {
         match self {
@@ -71,11 +366,23 @@ impl ToString for Error {
             }
 
             Error::InvalidBinding(sx) => {
-                return format!("invalid binding form in function, got {}", sx.to_string());
+                return format!("invalid binding form, got {}", sx.to_string());
             }
 
             Error::DuplicateBinding(symbol) => {
-                return format!("cannot bind symbol {} more than once in function definition", symbol);
+                return format!("cannot bind symbol {} more than once", symbol);
+            }
+
+            Error::RecursiveBindingNotFn(symbol, sx) => {
+                return format!("letrec only binds functions, got {} for {}", sx.to_string(), symbol);
+            }
+
+            Error::UnquoteOutsideQuasiquote(sx) => {
+                return format!("unquote outside of quasiquote: {}", sx.to_string());
+            }
+
+            Error::SpliceOutsideSequence(sx) => {
+                return format!("unquote-splicing outside of a list or vector: {}", sx.to_string());
             }
 
             Error::BuiltinTooFewArgs(name, min_arity, act_arity) => {
@@ -86,8 +393,21 @@ impl ToString for Error {
                 return format!("{} expects at most {} argument(s), got {}", name, max_arity, act_arity);
             }
 
-            Error::BuiltinBadArg(name, arg) => {
-                return format!("invalid argument to {}, got {}", name, arg.to_string());
+            Error::BuiltinBadArg(name, index, ty, arg) => {
+                let actual = match arg {
+                    Sx::Nil => arg.to_string(),
+                    _ => format!("{} {}", arg.type_name(), arg.to_string())
+                };
+
+                return format!("argument {} to {} must be {}, got {}", index + 1, name, ty.description(), actual);
+            }
+
+            Error::BuiltinArgOutOfRange(name, index, arg) => {
+                return format!("argument {} to {} is out of range, got {} {}", index + 1, name, arg.type_name(), arg.to_string());
+            }
+
+            Error::BuiltinUnpairedKey(name, key) => {
+                return format!("{} expects key/value pairs, got key {} without a value", name, key.to_string());
             }
 
             Error::FnTooFewArgs(f, min_arity, act_arity) => {
@@ -124,19 +444,19 @@ impl ToString for Error {
                 return format!("error while reading file for module {}: {}", module_name, io_error);
             }
 
-            Error::ModuleReadErrors(_module_name, read_errors) => {
+            Error::ModuleReadErrors(_module_name, source, read_errors) => {
                 let mut s = read_errors
                     .iter()
-                    .fold(String::new(), |acc, e| acc + &e.to_string() + "\n");
+                    .fold(String::new(), |acc, e| acc + &source.name + ":" + &e.to_string() + "\n");
                 s.pop();
 
                 return s;
             }
 
-            Error::ModuleEvalErrors(_module_name, eval_errors) => {
+            Error::ModuleEvalErrors(_module_name, _source, eval_errors) => {
                 let mut s = eval_errors
                     .iter()
-                    .fold(String::new(), |acc, e| acc + &e.to_string() + "\n");
+                    .fold(String::new(), |acc, (e, _)| acc + &e.to_string() + "\n");
                 s.pop();
 
                 return s;
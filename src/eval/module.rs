@@ -4,10 +4,12 @@ use std::io::prelude::*;
 use std::path::Path;
 use std::sync::Arc;
 
+use ::diagnostic::Source;
 use ::eval::env::Env;
 use ::eval::eval::eval;
-use ::eval::{Result, Error};
-use ::read::read;
+use ::eval::scope::Scope;
+use ::eval::{Result, Error, Warning};
+use ::read::read_forms;
 use ::sx::{Sx, SxSymbol};
 
 pub fn from_filename(filename: &str) -> SxSymbol {
@@ -66,7 +68,7 @@ pub fn load_use(env: &mut Env, module_name: &SxSymbol) -> Result {
     };
 
     let mut file = match File::open(filename) {
-        Ok(mut f)   => f,
+        Ok(f)       => f,
         Err(e)      => return Err(Error::ModuleIoOpenError(module_name.clone(), e.to_string()))
     };
 
@@ -76,30 +78,50 @@ pub fn load_use(env: &mut Env, module_name: &SxSymbol) -> Result {
         Err(e)  => return Err(Error::ModuleIoReadError(module_name.clone(), e.to_string()))
     }
 
-    let sxs = match read(source.as_ref()) {
-        Ok(xs) => xs,
+    let source = Source::new(filename, &source);
+    let forms = match read_forms(&source.text, &env.dispatch_table()) {
+        Ok(forms) => forms,
         Err(read_errors) => {
-            return Err(Error::ModuleReadErrors(module_name.clone(), read_errors))
+            return Err(Error::ModuleReadErrors(module_name.clone(), source, read_errors))
         }
     };
 
     let mut new_env = env.clone();
     new_env.current_module = module_name.clone();
     new_env.loaded_modules.insert(module_name.clone());
+    new_env.scope = Scope::new();
 
+    // Warnings raised before the module is loaded belong to the caller.
+    let pending_warnings = env.take_warnings();
+
+    let mut module_warnings = Vec::new();
     let mut eval_errors = Vec::new();
-    for sx in sxs.iter() {
-        match eval(&mut new_env, sx) {
+    for form in forms.into_iter() {
+        let result = eval(&mut new_env, &form.sx);
+        for warning in new_env.take_warnings() {
+            module_warnings.push((warning, form.clone()));
+        }
+
+        match result {
             Ok(_)           => (),
-            Err(eval_error) => eval_errors.push(eval_error)
+            Err(eval_error) => eval_errors.push((eval_error, form))
         }
     }
 
+    for warning in pending_warnings {
+        env.warn(warning);
+    }
+
+    if !module_warnings.is_empty() {
+        env.warn(Warning::ModuleWarnings(module_name.clone(), source.clone(), module_warnings));
+    }
+
     if !eval_errors.is_empty() {
-        return Err(Error::ModuleEvalErrors(module_name.clone(), eval_errors));
+        return Err(Error::ModuleEvalErrors(module_name.clone(), source, eval_errors));
     }
 
     new_env.current_module = env.current_module.clone();
+    new_env.scope = env.scope.clone();
     *env = new_env;
 
     return Ok(Sx::Symbol(module_name.clone()));
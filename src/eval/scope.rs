@@ -0,0 +1,274 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, OnceLock, Weak};
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+
+use ::sx::{Sx, SxSymbol};
+
+// Local bindings, such as function parameters, kept apart from module
+// definitions. Each frame points to the scope it was created in, so that
+// functions can capture the scope they are created in by reference.
+#[derive(Clone, Default)]
+pub struct Scope {
+    id:     u64,
+    link:   Option<Link>
+}
+
+// The functions bound by a recursive frame hold it weakly, since the frame
+// holds them in turn.
+#[derive(Clone)]
+enum Link {
+    Strong(Arc<Frame>),
+    Weak(Weak<Frame>)
+}
+
+// The bindings of a recursive frame are set once its values have been
+// evaluated.
+struct Frame {
+    bindings:   OnceLock<Vec<(SxSymbol, Sx)>>,
+    parent:     Scope
+}
+
+// Frames are numbered in the order they are created, so that functions
+// order the same way in every run of a program.
+static FRAME_COUNT: AtomicU64 = AtomicU64::new(0);
+
+impl Scope {
+    pub fn new() -> Scope {
+        return Scope {
+            id:     0,
+            link:   None
+        };
+    }
+
+    // A new scope with the given bindings on top of this one.
+    pub fn extend(&self, bindings: Vec<(SxSymbol, Sx)>) -> Scope {
+        let scope = self.extend_recursive();
+        scope.bind_recursive(bindings);
+        return scope;
+    }
+
+    // A new scope on top of this one whose bindings are given later with
+    // bind_recursive, so that functions created in its weak copy can refer
+    // to them once they are called.
+    pub fn extend_recursive(&self) -> Scope {
+        let frame = Frame {
+            bindings:   OnceLock::new(),
+            parent:     self.clone()
+        };
+
+        return Scope {
+            id:     FRAME_COUNT.fetch_add(1, AtomicOrdering::Relaxed) + 1,
+            link:   Some(Link::Strong(Arc::new(frame)))
+        };
+    }
+
+    // The same scope without keeping its frame alive.
+    pub fn weak(&self) -> Scope {
+        let link = match self.link {
+            Some(Link::Strong(ref frame)) => Some(Link::Weak(Arc::downgrade(frame))),
+            ref link => link.clone()
+        };
+
+        return Scope {
+            id: self.id,
+            link
+        };
+    }
+
+    pub fn bind_recursive(&self, bindings: Vec<(SxSymbol, Sx)>) {
+        match self.frame() {
+            Some(frame) => {
+                let result = frame.bindings.set(bindings);
+                assert!(result.is_ok());
+            },
+
+            None => {
+                assert!(false);
+            }
+        }
+    }
+
+    // The innermost binding of the symbol, if any. Functions that hold the
+    // frame they are bound in weakly are returned holding it strongly, so
+    // that they keep working once returned from a letrec.
+    pub fn lookup(&self, symbol: &SxSymbol) -> Option<Sx> {
+        let mut id = self.id;
+        let mut next_frame = self.frame();
+        while let Some(frame) = next_frame {
+            for (name, value) in frame.bindings.get().iter().flat_map(|bindings| bindings.iter().rev()) {
+                if name != symbol {
+                    continue;
+                }
+
+                match value {
+                    Sx::Function(f) if f.scope.id == id && f.scope.is_weak() => {
+                        let mut strong_f = f.as_ref().clone();
+                        strong_f.scope = Scope {
+                            id,
+                            link: Some(Link::Strong(frame.clone()))
+                        };
+
+                        return Some(Sx::Function(Arc::new(strong_f)));
+                    },
+
+                    _ => return Some(value.clone())
+                }
+            }
+
+            id = frame.parent.id;
+            next_frame = frame.parent.frame();
+        }
+
+        return None;
+    }
+
+    // The frame of the scope, unless the scope is empty or its frame no
+    // longer exists.
+    fn frame(&self) -> Option<Arc<Frame>> {
+        match self.link {
+            Some(Link::Strong(ref frame)) => return Some(frame.clone()),
+            Some(Link::Weak(ref frame)) => return frame.upgrade(),
+            None => return None
+        }
+    }
+
+    fn is_weak(&self) -> bool {
+        match self.link {
+            Some(Link::Weak(_)) => return true,
+            _ => return false
+        }
+    }
+}
+
+// Only the names are shown, since values may be functions that capture the
+// scope they are bound in.
+impl fmt::Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut frames = Vec::new();
+        let mut next_frame = self.frame();
+        while let Some(frame) = next_frame {
+            let names = frame.bindings
+                .get()
+                .iter()
+                .flat_map(|bindings| bindings.iter().map(|(name, _)| name.to_string()))
+                .collect::<Vec<_>>();
+            frames.push(names);
+            next_frame = frame.parent.frame();
+        }
+
+        return write!(f, "Scope({:?})", frames);
+    }
+}
+
+// Scopes compare by identity, since functions that capture different scopes
+// may behave differently even when their bindings look alike.
+impl PartialEq for Scope {
+    fn eq(&self, other: &Scope) -> bool {
+        return self.id == other.id;
+    }
+}
+
+impl Eq for Scope {}
+
+impl PartialOrd for Scope {
+    fn partial_cmp(&self, other: &Scope) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Ord for Scope {
+    fn cmp(&self, other: &Scope) -> Ordering {
+        return self.id.cmp(&other.id);
+    }
+}
+
+impl Hash for Scope {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ::sx::SxFunctionInfo;
+
+    #[test]
+    fn test_lookup() {
+        let x = sx_symbol_unwrapped!("x");
+        let y = sx_symbol_unwrapped!("y");
+
+        let outer = Scope::new().extend(vec![(x.clone(), sx_integer!(1)), (y.clone(), sx_integer!(2))]);
+        let inner = outer.extend(vec![(x.clone(), sx_integer!(3))]);
+
+        assert_eq!(None, Scope::new().lookup(&x));
+        assert_eq!(Some(sx_integer!(1)), outer.lookup(&x));
+        assert_eq!(Some(sx_integer!(3)), inner.lookup(&x));
+        assert_eq!(Some(sx_integer!(2)), inner.lookup(&y));
+        assert_eq!(None, inner.lookup(&sx_symbol_unwrapped!("z")));
+    }
+
+    #[test]
+    fn test_identity() {
+        let x = sx_symbol_unwrapped!("x");
+        let scope = Scope::new().extend(vec![(x.clone(), sx_integer!(1))]);
+
+        assert_eq!(Scope::new(), Scope::new());
+        assert_eq!(scope, scope.clone());
+        let later_scope = Scope::new().extend(vec![(x.clone(), sx_integer!(1))]);
+        assert!(scope != later_scope);
+        assert!(scope < later_scope);
+        assert!(Scope::new() < scope);
+    }
+
+    #[test]
+    fn test_recursive() {
+        let f = sx_symbol_unwrapped!("f");
+        let scope = Scope::new().extend_recursive();
+        assert_eq!(None, scope.lookup(&f));
+
+        scope.bind_recursive(vec![(f.clone(), sx_integer!(1))]);
+        assert_eq!(Some(sx_integer!(1)), scope.lookup(&f));
+        assert_eq!("Scope([[\"f\"]])", format!("{:?}", scope));
+    }
+
+    #[test]
+    fn test_recursive_function() {
+        let f = sx_symbol_unwrapped!("f");
+        let scope = Scope::new().extend_recursive();
+        let function = Sx::Function(Arc::new(SxFunctionInfo {
+            module:     sx_symbol_unwrapped!("test"),
+            arity:      0,
+            bindings:   vec![],
+            body:       Arc::new(vec![sx_list![sx_symbol!("f")]]),
+            scope:      scope.weak()
+        }));
+        scope.bind_recursive(vec![(f.clone(), function)]);
+
+        let frame = match scope.link {
+            Some(Link::Strong(ref frame)) => Arc::downgrade(frame),
+            _ => panic!("no frame")
+        };
+        assert_eq!(1, frame.strong_count());
+
+        let found = scope.lookup(&f).unwrap();
+        match found {
+            Sx::Function(ref found_f) => {
+                assert!(!found_f.scope.is_weak());
+                assert_eq!(scope, found_f.scope);
+            },
+
+            _ => panic!("not a function: {:?}", found)
+        }
+        assert_eq!(2, frame.strong_count());
+
+        drop(scope);
+        assert!(frame.upgrade().is_some());
+
+        drop(found);
+        assert!(frame.upgrade().is_none());
+    }
+}
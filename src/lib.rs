@@ -0,0 +1,32 @@
+// Lints against idioms the code base was written in before they existed,
+// such as explicit returns and assert!(false) for unreachable arms.
+#![allow(clippy::needless_return)]
+#![allow(clippy::assertions_on_constants)]
+#![allow(clippy::to_string_trait_impl)]
+#![allow(clippy::useless_format)]
+#![allow(clippy::single_match)]
+#![allow(clippy::enum_variant_names)]
+#![allow(clippy::module_inception)]
+#![allow(clippy::redundant_static_lifetimes)]
+#![allow(clippy::explicit_counter_loop)]
+#![allow(clippy::needless_range_loop)]
+#![allow(clippy::ptr_arg)]
+#![allow(clippy::needless_borrow)]
+#![allow(clippy::explicit_auto_deref)]
+#![allow(clippy::to_string_in_format_args)]
+
+#[macro_use] extern crate im;
+extern crate num_bigint;
+extern crate num_traits;
+extern crate time;
+extern crate unicode_segmentation;
+extern crate unicode_xid;
+
+#[cfg(test)]
+#[macro_use] extern crate pretty_assertions;
+
+#[macro_use] pub mod sx;
+pub mod diagnostic;
+pub mod eval;
+pub mod read;
+pub mod util;
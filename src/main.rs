@@ -1,18 +1,13 @@
 #[macro_use] extern crate clap;
-#[macro_use] extern crate im;
 extern crate rustyline;
+#[macro_use] extern crate solang;
 extern crate time;
-extern crate unicode_segmentation;
 
-#[cfg(test)]
-#[macro_use] extern crate pretty_assertions;
+// The front ends refer to the modules of the library from the crate root.
+use solang::{diagnostic, eval, read, util};
 
-#[macro_use] mod sx;
-mod eval;
-mod read;
 mod repl;
 mod script;
-mod util;
 
 use std::sync::Arc;
 use clap::App;
@@ -24,11 +19,16 @@ fn main() {
     let matches = App::from_yaml(yaml).get_matches();
 
     let interactive = matches.is_present("interactive");
+    let show_warnings = !matches.is_present("no-warnings");
 
 
     match matches.value_of("INPUT") {
+        Some("-") => {
+            script::run_stdin(show_warnings);
+        },
+
         Some(input) => {
-            script::run(input, interactive);
+            script::run(input, interactive, show_warnings);
         },
 
         None => {
@@ -38,7 +38,7 @@ fn main() {
 
             let current_module = sx_symbol_unwrapped!("repl");
             let mut env = Env::new(&module_paths, &current_module);
-            repl::enter(&mut env);
+            repl::enter(&mut env, show_warnings);
         }
     }
 }
@@ -0,0 +1,246 @@
+use ::read::span::{Position, Span};
+use ::read::token::{Kind, Token, TokenReader};
+
+// A node of a concrete syntax tree, which keeps every token of the source,
+// including whitespace and comments, so that the source can be rewritten
+// without losing its layout.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Node {
+    Leaf(Leaf),
+    Group(Group)
+}
+
+// A single token, or a run of whitespace between tokens.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Leaf {
+    pub kind:   Kind,
+    pub text:   String,
+    pub span:   Span
+}
+
+// Nodes between an opening delimiter and its closing delimiter, if any.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Group {
+    pub open:       Leaf,
+    pub children:   Vec<Node>,
+    pub close:      Option<Leaf>
+}
+
+// Builds a concrete syntax tree of any source, including sources with read
+// errors. A closing delimiter closes the innermost open group regardless of
+// its kind, and a closing delimiter with no open group is kept as a leaf.
+pub fn parse(source: &str) -> Vec<Node> {
+    let mut nodes = Vec::new();
+    let mut group_stack: Vec<(Vec<Node>, Leaf)> = Vec::new();
+    let mut position = Position::start();
+
+    for token in TokenReader::new(source, Position::start()) {
+        if token.span.start.offset > position.offset {
+            nodes.push(Node::Leaf(whitespace(source, position, token.span.start)));
+        }
+
+        position = token.span.end;
+
+        let leaf = token_leaf(source, &token);
+        match token.kind {
+            Kind::ListStart | Kind::VectorStart | Kind::MapStart | Kind::SetStart => {
+                group_stack.push((nodes, leaf));
+                nodes = Vec::new();
+            },
+
+            Kind::ListEnd | Kind::VectorEnd | Kind::MapEnd => {
+                match group_stack.pop() {
+                    Some((top_nodes, open)) => {
+                        let group = Group {
+                            open,
+                            children:   nodes,
+                            close:      Some(leaf)
+                        };
+
+                        nodes = top_nodes;
+                        nodes.push(Node::Group(group));
+                    },
+
+                    None => {
+                        nodes.push(Node::Leaf(leaf));
+                    }
+                }
+            },
+
+            _ => {
+                nodes.push(Node::Leaf(leaf));
+            }
+        }
+    }
+
+    let end = Position::start().advance(source);
+    if end.offset > position.offset {
+        nodes.push(Node::Leaf(whitespace(source, position, end)));
+    }
+
+    while let Some((top_nodes, open)) = group_stack.pop() {
+        let group = Group {
+            open,
+            children:   nodes,
+            close:      None
+        };
+
+        nodes = top_nodes;
+        nodes.push(Node::Group(group));
+    }
+
+    return nodes;
+}
+
+// Returns the exact source text of the given nodes.
+pub fn unparse(nodes: &[Node]) -> String {
+    let mut s = String::new();
+    for node in nodes.iter() {
+        s.push_str(node.to_string().as_ref());
+    }
+
+    return s;
+}
+
+impl Node {
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Leaf(leaf) => {
+                return leaf.span;
+            },
+
+            Node::Group(group) => {
+                let end = match (&group.close, group.children.last()) {
+                    (Some(close), _) => close.span.end,
+                    (None, Some(last)) => last.span().end,
+                    (None, None) => group.open.span.end
+                };
+
+                return Span::new(group.open.span.start, end);
+            }
+        }
+    }
+}
+
+impl ToString for Node {
+    fn to_string(&self) -> String {
+        match self {
+            Node::Leaf(leaf) => {
+                return leaf.text.clone();
+            },
+
+            Node::Group(group) => {
+                let mut s = group.open.text.clone();
+                s.push_str(unparse(&group.children).as_ref());
+                if let Some(ref close) = group.close {
+                    s.push_str(close.text.as_ref());
+                }
+
+                return s;
+            }
+        }
+    }
+}
+
+fn token_leaf(source: &str, token: &Token) -> Leaf {
+    return Leaf {
+        kind:   token.kind,
+        text:   source[token.span.start.offset .. token.span.end.offset].to_string(),
+        span:   token.span
+    };
+}
+
+fn whitespace(source: &str, start: Position, end: Position) -> Leaf {
+    return Leaf {
+        kind:   Kind::Whitespace,
+        text:   source[start.offset .. end.offset].to_string(),
+        span:   Span::new(start, end)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::path::Path;
+
+    fn test_round_trip(source: &str) {
+        assert_eq!(source, unparse(&parse(source)));
+    }
+
+    fn source_files(dir: &Path, files: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).expect("failed to read directory") {
+            let path = entry.expect("failed to read directory entry").path();
+            if path.is_dir() {
+                source_files(&path, files);
+            } else if path.extension().is_some_and(|extension| extension == "sol") {
+                files.push(path.to_str().expect("invalid path").to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_examples() {
+        let mut files = Vec::new();
+        source_files(&Path::new(env!("CARGO_MANIFEST_DIR")).join("examples"), &mut files);
+        assert!(!files.is_empty());
+
+        for file in files.iter() {
+            let source = fs::read_to_string(file).expect("failed to read example");
+            test_round_trip(&source);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_trivia() {
+        test_round_trip("");
+        test_round_trip("  \n\t ");
+        test_round_trip("; comment\n(foo  #| block |# bar) #_ baz\r\n");
+        test_round_trip("'[1 \"a\\\"b\"]  `(,x ,@y) {:a #{1}}\n");
+        test_round_trip("北京 \\北 \"市\n\"");
+        test_round_trip("#r##\"a\\\"#\"## \"\"\"\n  b\n  \"\"\" #r\"c");
+    }
+
+    #[test]
+    fn test_round_trip_errors() {
+        test_round_trip("(foo [bar");
+        test_round_trip("foo)) ]");
+        test_round_trip("(foo] \"bar");
+        test_round_trip("#| unterminated");
+        test_round_trip("#( ,,, 0xZZ");
+    }
+
+    #[test]
+    fn test_structure() {
+        let nodes = parse("(a ; b\n [c])  ");
+        assert_eq!(2, nodes.len());
+
+        let group = match &nodes[0] {
+            Node::Group(group) => group,
+            _ => panic!("expected group")
+        };
+
+        assert_eq!("(", group.open.text);
+        assert_eq!(Some(")".to_string()), group.close.as_ref().map(|close| close.text.clone()));
+        assert_eq!(vec![Kind::Symbol, Kind::Whitespace, Kind::LineComment, Kind::Whitespace, Kind::VectorStart],
+                   group.children.iter().map(|node| {
+                       match node {
+                           Node::Leaf(leaf) => leaf.kind,
+                           Node::Group(group) => group.open.kind
+                       }
+                   }).collect::<Vec<_>>());
+
+        assert_eq!(Span::new(Position::new(0, 1, 1), Position::new(12, 2, 6)), nodes[0].span());
+        assert_eq!(Span::new(Position::new(8, 2, 2), Position::new(11, 2, 5)), group.children[4].span());
+
+        match &nodes[1] {
+            Node::Leaf(leaf) => {
+                assert_eq!(Kind::Whitespace, leaf.kind);
+                assert_eq!("  ", leaf.text);
+            },
+
+            _ => panic!("expected whitespace")
+        }
+    }
+}
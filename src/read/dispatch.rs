@@ -0,0 +1,222 @@
+use std::sync::Arc;
+
+use im;
+
+use ::sx::{Sx, SxTaggedInfo};
+
+// Reads the form following a tag into a value, or returns a message
+// describing why the form is not valid for the tag.
+pub type Handler = Arc<dyn Fn(&Sx) -> Result<Sx, String>>;
+
+// Handlers for tagged literals, keyed by tag name without the leading hash,
+// e.g. #inst "2018-11-05T13:15:30Z" is read by the handler of "inst".
+#[derive(Clone)]
+pub struct DispatchTable {
+    handlers: im::HashMap<String, Handler>
+}
+
+type BuiltinHandler = fn(&Sx) -> Result<Sx, String>;
+
+static BUILTIN_HANDLERS: &'static [(&'static str, BuiltinHandler)] = &[
+    ("inst",    read_inst),
+    ("uuid",    read_uuid)
+];
+
+impl Default for DispatchTable {
+    fn default() -> DispatchTable {
+        return DispatchTable::new();
+    }
+}
+
+impl DispatchTable {
+    // Returns a table with the built-in handlers.
+    pub fn new() -> DispatchTable {
+        let mut table = DispatchTable {
+            handlers: im::HashMap::new()
+        };
+
+        for (tag, handler) in BUILTIN_HANDLERS.iter() {
+            table.register(tag, *handler);
+        }
+
+        return table;
+    }
+
+    // Registers a handler for a tag, replacing any previous handler.
+    pub fn register<F>(&mut self, tag: &str, handler: F)
+        where F: Fn(&Sx) -> Result<Sx, String> + 'static
+    {
+        self.handlers.insert(tag.to_string(), Arc::new(handler));
+    }
+
+    pub fn handler(&self, tag: &str) -> Option<Handler> {
+        return self.handlers.get(tag).cloned();
+    }
+}
+
+// Reads an RFC 3339 date, or date and time with a UTC offset,
+// e.g. #inst "2018-11-05" or #inst "2018-11-05T13:15:30.250+02:00".
+fn read_inst(value: &Sx) -> Result<Sx, String> {
+    let s = match value {
+        Sx::String(s) => s.to_uppercase(),
+        _ => return Err(format!("expected a string, got {}", value.to_string()))
+    };
+
+    if !is_date(&s) && !is_date_time(&s) {
+        return Err(format!("expected an RFC 3339 timestamp, got {}", value.to_string()));
+    }
+
+    return Ok(sx_tagged!("inst", sx_string!(s)));
+}
+
+// Reads a UUID of hexadecimal digits in groups of 8, 4, 4, 4 and 12,
+// e.g. #uuid "f81d4fae-7dec-11d0-a765-00a0c91e6bf6".
+fn read_uuid(value: &Sx) -> Result<Sx, String> {
+    let s = match value {
+        Sx::String(s) => s.to_lowercase(),
+        _ => return Err(format!("expected a string, got {}", value.to_string()))
+    };
+
+    let groups = s.split('-').map(|group| group.len()).collect::<Vec<_>>();
+    if groups != [8, 4, 4, 4, 12] || !s.chars().all(|c| c == '-' || c.is_ascii_hexdigit()) {
+        return Err(format!("expected a UUID, got {}", value.to_string()));
+    }
+
+    return Ok(sx_tagged!("uuid", sx_string!(s)));
+}
+
+// YYYY-MM-DD
+fn is_date(s: &str) -> bool {
+    let (year, month, day) = match (number(s, 0, 4), number(s, 5, 2), number(s, 8, 2)) {
+        (Some(year), Some(month), Some(day)) => (year, month, day),
+        _ => return false
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false
+    };
+
+    return s.len() == 10 && &s[4 .. 5] == "-" && &s[7 .. 8] == "-" && 1 <= day && day <= days;
+}
+
+// YYYY-MM-DDTHH:MM:SS[.F...](Z|+HH:MM|-HH:MM), with a leap second allowed.
+fn is_date_time(s: &str) -> bool {
+    if s.len() < 20 || !s.is_char_boundary(10) || !is_date(&s[.. 10]) {
+        return false;
+    }
+
+    let is_time = match (number(s, 11, 2), number(s, 14, 2), number(s, 17, 2)) {
+        (Some(hour), Some(minute), Some(second)) => hour < 24 && minute < 60 && second <= 60,
+        _ => false
+    };
+
+    if !is_time || &s[10 .. 11] != "T" || &s[13 .. 14] != ":" || &s[16 .. 17] != ":" {
+        return false;
+    }
+
+    let mut rest = &s[19 ..];
+    if rest.starts_with('.') {
+        let fraction_size = rest[1 ..].chars().take_while(|c| c.is_ascii_digit()).count();
+        if fraction_size == 0 {
+            return false;
+        }
+
+        rest = &rest[1 + fraction_size ..];
+    }
+
+    if rest == "Z" {
+        return true;
+    }
+
+    let is_offset = match (number(rest, 1, 2), number(rest, 4, 2)) {
+        (Some(hour), Some(minute)) => hour < 24 && minute < 60,
+        _ => false
+    };
+
+    return is_offset && rest.len() == 6 && (rest.starts_with('+') || rest.starts_with('-')) && &rest[3 .. 4] == ":";
+}
+
+// Reads a number of exactly the given count of decimal digits at an offset.
+fn number(s: &str, offset: usize, size: usize) -> Option<u32> {
+    match s.get(offset .. offset + size) {
+        Some(digits) if digits.chars().all(|c| c.is_ascii_digit()) => return digits.parse().ok(),
+        _ => return None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_tagged(tag: &str, s: &str) -> Result<Sx, String> {
+        let handler = DispatchTable::new().handler(tag).expect("missing handler");
+        return handler(&sx_string!(s));
+    }
+
+    #[test]
+    fn test_inst() {
+        let valid = vec![
+            "2018-11-05",
+            "2016-02-29",
+            "2018-11-05T13:15:30Z",
+            "2018-11-05T23:59:60.250+02:00",
+            "2018-11-05t13:15:30.1-11:30"
+        ];
+
+        for s in valid {
+            assert_eq!(Ok(sx_tagged!("inst", sx_string!(s.to_uppercase()))), read_tagged("inst", s));
+        }
+
+        let invalid = vec![
+            "",
+            "2018-1-05",
+            "2018-13-05",
+            "2018-02-29",
+            "2018-11-05T24:00:00Z",
+            "2018-11-05T13:15:30",
+            "2018-11-05T13:15:30.Z",
+            "2018-11-05T13:15:30+0200",
+            "2018-11-05 13:15:30Z",
+            "２018-11-05"
+        ];
+
+        for s in invalid {
+            assert!(read_tagged("inst", s).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_uuid() {
+        assert_eq!(Ok(sx_tagged!("uuid", sx_string!("f81d4fae-7dec-11d0-a765-00a0c91e6bf6"))),
+                   read_tagged("uuid", "F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6"));
+
+        assert!(read_tagged("uuid", "f81d4fae-7dec-11d0-a765-00a0c91e6bf").is_err());
+        assert!(read_tagged("uuid", "f81d4fae7dec-11d0-a765-00a0c91e6bf6-").is_err());
+        assert!(read_tagged("uuid", "g81d4fae-7dec-11d0-a765-00a0c91e6bf6").is_err());
+
+        let handler = DispatchTable::new().handler("uuid").unwrap();
+        assert!(handler(&sx_integer!(1)).is_err());
+    }
+
+    #[test]
+    fn test_register() {
+        let mut table = DispatchTable::new();
+        assert!(table.handler("twice").is_none());
+
+        table.register("twice", |value| {
+            match value {
+                Sx::Integer(i) => Ok(sx_integer!(i * 2)),
+                _ => Err("expected an integer".to_string())
+            }
+        });
+
+        let handler = table.handler("twice").expect("missing handler");
+        assert_eq!(Ok(sx_integer!(42)), handler(&sx_integer!(21)));
+        assert_eq!(Err("expected an integer".to_string()), handler(&sx_nil!()));
+    }
+}
@@ -1,44 +1,206 @@
+// Not used by the interpreter itself, but by tools that rewrite source.
+#[allow(dead_code)]
+pub mod cst;
+pub mod dispatch;
+pub mod span;
+pub mod stream;
+
 mod parse;
-mod token;
+pub mod token;
 
 use ::std::result;
 
+use ::diagnostic::Diagnostic;
+use ::read::dispatch::DispatchTable;
 use ::read::parse::parse;
+use ::read::span::Span;
 use ::read::token::Kind;
 use ::sx::Sx;
 
+// Not used by the interpreter itself, which reads with the handlers of its
+// environment.
+#[allow(dead_code)]
 pub fn read(source: &str) -> Result {
-    return parse(source);
+    return read_with(source, &DispatchTable::new());
+}
+
+// Reads tagged literals with the handlers of the given table.
+pub fn read_with(source: &str, dispatch: &DispatchTable) -> Result {
+    return read_forms(source, dispatch)
+        .map(|forms| forms.into_iter().map(|form| form.sx).collect());
+}
+
+pub fn read_forms(source: &str, dispatch: &DispatchTable) -> FormResult {
+    return parse(source, dispatch);
 }
 
 pub type Result = result::Result<Vec<Sx>, Vec<Error>>;
+pub type FormResult = result::Result<Vec<Form>, Vec<Error>>;
+
+// A read value along with its source span and the forms it was read from,
+// e.g. the elements of a list or the quoted value of a quote.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Form {
+    pub sx:         Sx,
+    pub span:       Span,
+    pub children:   Vec<Form>
+}
 
 #[derive(Eq, PartialEq, Debug)]
 pub enum Error {
-    InvalidToken(String),
-    IntegerLimit(String),
-    PartialString(String),
-    InvalidCloseDelimiter(Kind, String),
-    TrailingDelimiter(String),
-    UnmatchedDelimiter(Kind)
+    InvalidToken(String, Span),
+    PartialString(String, Span),
+    InvalidEscape(String, Span),
+    InvalidChar(String, Span),
+    PartialComment(String, Span),
+    // The span of the close delimiter, then of the open delimiter.
+    InvalidCloseDelimiter(Kind, String, Span, Span),
+    TrailingDelimiter(String, Span),
+    UnmatchedDelimiter(Kind, Span),
+    OddMapForms(Span),
+    // The span of the repeated form, then of its first occurrence.
+    DuplicateMapKey(String, Span, Span),
+    DuplicateSetElement(String, Span, Span),
+    UnknownTag(String, Span),
+    InvalidTaggedLiteral(String, String, Span)
 }
 
-impl ToString for Error {
-    fn to_string(&self) -> String {
+impl Error {
+    pub fn span(&self) -> Span {
         match self {
-            Error::InvalidToken(s) => {
-                return format!("invalid token: {}", s)
+            Error::InvalidToken(_, span)                |
+            Error::PartialString(_, span)               |
+            Error::InvalidEscape(_, span)               |
+            Error::InvalidChar(_, span)                 |
+            Error::PartialComment(_, span)              |
+            Error::InvalidCloseDelimiter(_, _, span, _) |
+            Error::TrailingDelimiter(_, span)           |
+            Error::UnmatchedDelimiter(_, span)          |
+            Error::OddMapForms(span)                    |
+            Error::DuplicateMapKey(_, span, _)          |
+            Error::DuplicateSetElement(_, span, _)      |
+            Error::UnknownTag(_, span)                  |
+            Error::InvalidTaggedLiteral(_, _, span)     => *span
+        }
+    }
+
+    // Whether more input could resolve the error.
+    pub fn is_incomplete(&self) -> bool {
+        return matches!(self, Error::UnmatchedDelimiter(_, _) | Error::PartialString(_, _) | Error::PartialComment(_, _));
+    }
+
+    // Stable codes, so that new errors get new codes. R0002 was used for
+    // integer literals out of range, which are now read as big integers.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidToken(_, _)                   => "R0001",
+            Error::PartialString(_, _)                  => "R0003",
+            Error::InvalidEscape(_, _)                  => "R0004",
+            Error::InvalidChar(_, _)                    => "R0005",
+            Error::PartialComment(_, _)                 => "R0006",
+            Error::InvalidCloseDelimiter(_, _, _, _)    => "R0007",
+            Error::TrailingDelimiter(_, _)              => "R0008",
+            Error::UnmatchedDelimiter(_, _)             => "R0009",
+            Error::OddMapForms(_)                       => "R0010",
+            Error::DuplicateMapKey(_, _, _)             => "R0011",
+            Error::DuplicateSetElement(_, _, _)         => "R0012",
+            Error::UnknownTag(_, _)                     => "R0013",
+            Error::InvalidTaggedLiteral(_, _, _)        => "R0014"
+        }
+    }
+
+    pub fn diagnostic(&self) -> Diagnostic {
+        let diagnostic = Diagnostic::error(self.code(), &self.message());
+        let span = self.span();
+
+        match self {
+            Error::PartialString(_, _) => {
+                let quote_span = Span::new(span.start, span.start.advance("\""));
+                return diagnostic.with_primary(quote_span, "string starts here");
+            },
+
+            Error::InvalidEscape(_, _) => {
+                return diagnostic
+                    .with_primary(span, "unknown escape")
+                    .with_help("use raw strings like #r\"\\d\" for backslashes without escapes");
+            },
+
+            Error::PartialComment(_, _) => {
+                let open_span = Span::new(span.start, span.start.advance("#|"));
+                return diagnostic.with_primary(open_span, "comment starts here");
             },
 
-            Error::IntegerLimit(s) => {
-                return format!("integer limit: {}", s)
+            Error::InvalidCloseDelimiter(kind, _, _, open_span) => {
+                let (name, close) = delimiter(*kind);
+                return diagnostic
+                    .with_primary(span, &format!("expected '{}'", close))
+                    .with_secondary(*open_span, &format!("{} opened here", name));
             },
 
-            Error::PartialString(s) => {
+            Error::TrailingDelimiter(_, _) => {
+                return diagnostic.with_primary(span, "nothing to close");
+            },
+
+            Error::UnmatchedDelimiter(kind, _) => {
+                let (name, close) = delimiter(*kind);
+                return diagnostic
+                    .with_primary(span, &format!("{} opened here", name))
+                    .with_help(&format!("close it with '{}'", close));
+            },
+
+            Error::OddMapForms(_) => {
+                return diagnostic
+                    .with_primary(span, "")
+                    .with_note("each key must be followed by a value");
+            },
+
+            Error::DuplicateMapKey(_, _, first_span) => {
+                return diagnostic
+                    .with_primary(span, "repeated here")
+                    .with_secondary(*first_span, "first used here");
+            },
+
+            Error::DuplicateSetElement(_, _, first_span) => {
+                return diagnostic
+                    .with_primary(span, "repeated here")
+                    .with_secondary(*first_span, "first used here");
+            },
+
+            Error::UnknownTag(tag, _) => {
+                return diagnostic
+                    .with_primary(span, "")
+                    .with_help(&format!("register a reader for it with (defreader {} ...)", &tag[1 ..]));
+            },
+
+            _ => {
+                return diagnostic.with_primary(span, "");
+            }
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Error::InvalidToken(s, _) => {
+                return format!("invalid token: {}", s)
+            },
+
+            Error::PartialString(s, _) => {
                 return format!("non-terminated string: \"{}", s)
             }
 
-            Error::InvalidCloseDelimiter(kind, s) => {
+            Error::InvalidEscape(s, _) => {
+                return format!("invalid string escape: {}", s)
+            }
+
+            Error::InvalidChar(s, _) => {
+                return format!("invalid character literal: {}", s)
+            }
+
+            Error::PartialComment(s, _) => {
+                return format!("non-terminated block comment: {}", s)
+            }
+
+            Error::InvalidCloseDelimiter(kind, s, _, _) => {
                 match kind {
                     Kind::ListStart => {
                         return format!("invalid list close delimiter: '{}'", s)
@@ -48,6 +210,14 @@ impl ToString for Error {
                         return format!("invalid vector close delimiter: '{}'", s)
                     },
 
+                    Kind::MapStart => {
+                        return format!("invalid map close delimiter: '{}'", s)
+                    },
+
+                    Kind::SetStart => {
+                        return format!("invalid set close delimiter: '{}'", s)
+                    },
+
                     _ => {
                         assert!(false);
                         return "".to_string();
@@ -55,11 +225,11 @@ impl ToString for Error {
                 }
             },
 
-            Error::TrailingDelimiter(s) => {
+            Error::TrailingDelimiter(s, _) => {
                 return format!("trailing delimiter: '{}'", s)
             },
 
-            Error::UnmatchedDelimiter(kind) => {
+            Error::UnmatchedDelimiter(kind, _) => {
                 match kind {
                     Kind::ListStart => {
                         return format!("non-terminated list")
@@ -69,12 +239,56 @@ impl ToString for Error {
                         return format!("non-terminated vector")
                     },
 
+                    Kind::MapStart => {
+                        return format!("non-terminated map")
+                    },
+
+                    Kind::SetStart => {
+                        return format!("non-terminated set")
+                    },
+
                     _ => {
                         assert!(false);
                         return "".to_string();
                     }
                 }
+            },
+
+            Error::OddMapForms(_) => {
+                return format!("map literal must contain an even number of forms")
+            },
+
+            Error::DuplicateMapKey(s, _, _) => {
+                return format!("duplicate map key: {}", s)
+            },
+
+            Error::DuplicateSetElement(s, _, _) => {
+                return format!("duplicate set element: {}", s)
+            },
+
+            Error::UnknownTag(s, _) => {
+                return format!("no reader for tag: {}", s)
+            },
+
+            Error::InvalidTaggedLiteral(tag, message, _) => {
+                return format!("invalid #{} literal: {}", tag, message)
             }
         }
     }
 }
+
+// The name and close delimiter of a collection, by its open delimiter.
+fn delimiter(kind: Kind) -> (&'static str, char) {
+    match kind {
+        Kind::VectorStart   => return ("vector", ']'),
+        Kind::MapStart      => return ("map", '}'),
+        Kind::SetStart      => return ("set", '}'),
+        _                   => return ("list", ')')
+    }
+}
+
+impl ToString for Error {
+    fn to_string(&self) -> String {
+        return format!("{}: {}", self.span(), self.message());
+    }
+}
@@ -1,24 +1,58 @@
+use std::mem;
 use std::string::ToString;
 use std::sync::Arc;
 
 use im;
+use num_bigint::BigInt;
+use unicode_segmentation::UnicodeSegmentation;
 
-use ::sx::Sx;
+use ::sx::{Sx, SxFloat, SxSet};
 use ::read;
-use ::read::Error;
-use ::read::token::{tokenize, Kind};
+use ::read::{Error, Form};
+use ::read::dispatch::{DispatchTable, Handler};
+use ::read::span::{Position, Span};
+use ::read::token::{Kind, Token, TokenReader};
 
-pub fn parse(source: &str) -> read::Result {
-    let mut opt_sx = None;
-    let mut sxs = Vec::new();
+pub fn parse(source: &str, dispatch: &DispatchTable) -> read::FormResult {
+    return parse_at(source, Position::start(), dispatch);
+}
+
+pub fn parse_at(source: &str, start: Position, dispatch: &DispatchTable) -> read::FormResult {
+    let mut parser = Parser::new(start);
+    for token in TokenReader::new(source, start) {
+        parser.push(&token, dispatch);
+    }
 
-    let mut read_stack = Vec::new();
-    let mut nquotes = 0;
+    return parser.finish();
+}
 
-    let mut read_errors = Vec::new();
+// Reads forms one token at a time, so that the streaming reader can keep
+// the forms left open by a chunk of input and go on with the next chunk.
+pub struct Parser {
+    forms:          Vec<Form>,
+    read_stack:     Vec<(Vec<Form>, Kind, Span, Vec<Prefix>)>,
+    prefixes:       Vec<Prefix>,
+    errors:         Vec<Error>,
+
+    // The end of the last top-level form read, or the start of the source
+    // before any is.
+    complete_end:   Position
+}
+
+impl Parser {
+    pub fn new(start: Position) -> Parser {
+        return Parser {
+            forms:          Vec::new(),
+            read_stack:     Vec::new(),
+            prefixes:       Vec::new(),
+            errors:         Vec::new(),
+            complete_end:   start
+        };
+    }
+
+    pub fn push(&mut self, token: &Token, dispatch: &DispatchTable) {
+        let mut opt_sx = None;
 
-    let tokens = tokenize(source);
-    for token in tokens.iter() {
         match token.kind {
             Kind::Nil => {
                 opt_sx = Some(sx_nil!());
@@ -41,13 +75,25 @@ pub fn parse(source: &str) -> read::Result {
             }
 
             Kind::Integer => {
-                match token.data.parse::<i64>() {
-                    Ok(i) => {
-                        opt_sx = Some(sx_integer!(i));
+                match read_integer(&token) {
+                    Ok(sx) => {
+                        opt_sx = Some(sx);
+                    },
+
+                    Err(error) => {
+                        self.errors.push(error);
+                    }
+                }
+            },
+
+            Kind::Float => {
+                match token.data.parse::<f64>() {
+                    Ok(f) => {
+                        opt_sx = Some(sx_float!(f));
                     },
 
                     Err(_) => {
-                        read_errors.push(Error::IntegerLimit(token.data.to_string()));
+                        self.errors.push(Error::InvalidToken(token.data.to_string(), token.span));
                     }
                 }
             },
@@ -56,65 +102,167 @@ pub fn parse(source: &str) -> read::Result {
                 opt_sx = Some(sx_symbol!(token.data));
             },
 
+            Kind::Keyword => {
+                opt_sx = Some(sx_keyword!(&token.data[1 ..]));
+            },
+
+            Kind::Char => {
+                match read_char(&token.data[1 ..]) {
+                    Some(c) => {
+                        opt_sx = Some(sx_char!(c));
+                    },
+
+                    None => {
+                        self.errors.push(Error::InvalidChar(token.data.to_string(), token.span));
+                    }
+                }
+            },
+
             Kind::String => {
+                match unescape(token.data) {
+                    Ok(s) => {
+                        opt_sx = Some(sx_string!(s));
+                    },
+
+                    Err((offset, escape)) => {
+                        let start = token.span.start.advance("\"").advance(&token.data[.. offset]);
+                        let end = start.advance(escape);
+                        self.errors.push(Error::InvalidEscape(escape.to_string(), Span::new(start, end)));
+                    }
+                }
+            },
+
+            Kind::RawString => {
                 opt_sx = Some(sx_string!(token.data));
             },
 
-            Kind::ListStart | Kind::VectorStart => {
-                read_stack.push((sxs, token.kind, nquotes));
+            // Escapes are checked before the indentation is removed, so that
+            // invalid escapes are reported where they are in the source.
+            Kind::MultiLineString => {
+                match unescape(token.data) {
+                    Ok(_) => {
+                        opt_sx = unescape(&dedent(token.data)).ok().map(|s| sx_string!(s));
+                    },
 
-                let sub_sxs = Vec::new();
-                sxs = sub_sxs;
-                nquotes = 0;
+                    Err((offset, escape)) => {
+                        let start = token.span.start.advance("\"\"\"").advance(&token.data[.. offset]);
+                        let end = start.advance(escape);
+                        self.errors.push(Error::InvalidEscape(escape.to_string(), Span::new(start, end)));
+                    }
+                }
             },
 
-            Kind::ListEnd | Kind::VectorEnd => {
-                match read_stack.pop () {
-                    Some((mut top_sxs, top_delim, top_nquotes)) => {
+            Kind::ListStart | Kind::VectorStart | Kind::MapStart | Kind::SetStart => {
+                let forms = mem::take(&mut self.forms);
+                let prefixes = mem::take(&mut self.prefixes);
+                self.read_stack.push((forms, token.kind, token.span, prefixes));
+            },
+
+            Kind::ListEnd | Kind::VectorEnd | Kind::MapEnd => {
+                match self.read_stack.pop() {
+                    Some((top_forms, top_delim, top_span, top_prefixes)) => {
                         match (top_delim, token.kind) {
                             (Kind::ListStart, Kind::ListEnd) => (),
                             (Kind::VectorStart, Kind::VectorEnd) => (),
+                            (Kind::MapStart, Kind::MapEnd) => (),
+                            (Kind::SetStart, Kind::MapEnd) => (),
                             _ => {
-                                read_errors.push(Error::InvalidCloseDelimiter(top_delim, token.data.to_string()));
-                                continue;
+                                self.errors.push(Error::InvalidCloseDelimiter(top_delim, token.data.to_string(), token.span, top_span));
+                                return;
                             }
                         }
 
-                        let mut sx = match token.kind {
-                            Kind::ListEnd => sx_list_from_vec!(sxs),
-                            Kind::VectorEnd => sx_vector_from_vec!(sxs),
+                        let span = Span::new(top_span.start, token.span.end);
+
+                        let forms = mem::replace(&mut self.forms, top_forms);
+                        self.prefixes = top_prefixes;
+
+                        let sxs = forms
+                            .iter()
+                            .map(|form| form.sx.clone())
+                            .collect::<Vec<_>>();
+
+                        let sx_result = match top_delim {
+                            Kind::ListStart => Ok(sx_list_from_vec!(sxs)),
+                            Kind::VectorStart => Ok(sx_vector_from_vec!(sxs)),
+                            Kind::MapStart => read_map(&forms, span),
+                            Kind::SetStart => read_set(&forms),
                             _ => {
                                 assert!(false);
-                                sx_nil!()
+                                Ok(sx_nil!())
                             }
                         };
 
-                        nquotes = top_nquotes;
-                        for _ in 0 .. nquotes {
-                            sx = sx_quote!(sx);
-                            nquotes -= 1;
-                        }
+                        let sx = match sx_result {
+                            Ok(sx) => sx,
+                            Err(error) => {
+                                self.errors.push(error);
+                                return;
+                            }
+                        };
+
+                        let form = Form {
+                            sx,
+                            span,
+                            children:   forms
+                        };
 
-                        top_sxs.push(sx.clone());
-                        sxs = top_sxs
+                        push_form(&mut self.forms, form, &mut self.prefixes, &mut self.errors);
                     },
 
                     None => {
-                        read_errors.push(Error::TrailingDelimiter(token.data.to_string()));
+                        self.errors.push(Error::TrailingDelimiter(token.data.to_string(), token.span));
                     }
                 }
             },
 
             Kind::Quote => {
-                nquotes += 1;
+                self.prefixes.push(Prefix::Quote(token.span));
+            },
+
+            Kind::Quasiquote => {
+                self.prefixes.push(Prefix::Wrap("quasiquote", token.span));
+            },
+
+            Kind::Unquote => {
+                self.prefixes.push(Prefix::Wrap("unquote", token.span));
+            },
+
+            Kind::UnquoteSplicing => {
+                self.prefixes.push(Prefix::Wrap("unquote-splicing", token.span));
+            },
+
+            Kind::DatumComment => {
+                self.prefixes.push(Prefix::Discard);
+            },
+
+            // A tag without a handler discards the form it tags.
+            Kind::Tag => {
+                let tag = &token.data[1 ..];
+                match dispatch.handler(tag) {
+                    Some(handler) => {
+                        self.prefixes.push(Prefix::Tag(tag.to_string(), handler, token.span));
+                    },
+
+                    None => {
+                        self.errors.push(Error::UnknownTag(token.data.to_string(), token.span));
+                        self.prefixes.push(Prefix::Discard);
+                    }
+                }
+            },
+
+            Kind::LineComment | Kind::BlockComment => (),
+
+            Kind::BlockCommentPartial => {
+                self.errors.push(Error::PartialComment(token.data.to_string(), token.span));
             },
 
             Kind::StringPartial => {
-                read_errors.push(Error::PartialString(token.data.to_string()));
+                self.errors.push(Error::PartialString(token.data.to_string(), token.span));
             },
 
             Kind::Invalid => {
-                read_errors.push(Error::InvalidToken(token.data.to_string()));
+                self.errors.push(Error::InvalidToken(token.data.to_string(), token.span));
             },
 
             _ => {
@@ -123,43 +271,334 @@ pub fn parse(source: &str) -> read::Result {
         }
 
         match opt_sx {
-            Some(mut sx) => {
-                for _ in 0 .. nquotes {
-                    sx = sx_quote!(sx);
-                    nquotes -= 1;
+            Some(sx) => {
+                let form = Form {
+                    sx,
+                    span:       token.span,
+                    children:   Vec::new()
+                };
+
+                push_form(&mut self.forms, form, &mut self.prefixes, &mut self.errors);
+            },
+
+            None => ()
+        }
+
+        let is_partial = token.kind == Kind::StringPartial || token.kind == Kind::BlockCommentPartial;
+        if self.read_stack.is_empty() && self.prefixes.is_empty() && !is_partial {
+            self.complete_end = token.span.end;
+        }
+    }
+
+    // Removes and returns the top-level forms read so far.
+    pub fn take_forms(&mut self) -> Vec<Form> {
+        match self.read_stack.first_mut() {
+            Some((forms, _, _, _)) => return mem::take(forms),
+            None => return mem::take(&mut self.forms)
+        }
+    }
+
+    // Removes and returns the errors found so far.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        return mem::take(&mut self.errors);
+    }
+
+    pub fn complete_end(&self) -> Position {
+        return self.complete_end;
+    }
+
+    // Reports the forms left open as errors once there is no more input.
+    pub fn finish(mut self) -> read::FormResult {
+        for (_, top_delim, top_span, _) in self.read_stack.iter() {
+            self.errors.push(Error::UnmatchedDelimiter(*top_delim, *top_span));
+        }
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        return Ok(self.forms);
+    }
+}
+
+fn read_integer(token: &Token) -> Result<Sx, Error> {
+    let (sign, unsigned) = match token.data.starts_with('-') {
+        true => ("-", &token.data[1 ..]),
+        false => ("", token.data)
+    };
+
+    let (radix, digits) = match unsigned.get(.. 2) {
+        Some("0x") => (16, &unsigned[2 ..]),
+        Some("0o") => (8, &unsigned[2 ..]),
+        Some("0b") => (2, &unsigned[2 ..]),
+        _ => (10, unsigned)
+    };
+
+    let is_valid = !digits.is_empty()
+        && !digits.starts_with('_')
+        && !digits.ends_with('_')
+        && !digits.contains("__")
+        && digits.chars().all(|c| c == '_' || c.is_digit(radix));
+
+    if !is_valid {
+        return Err(Error::InvalidToken(token.data.to_string(), token.span));
+    }
+
+    let number = sign.to_string() + &digits.replace('_', "");
+    match i64::from_str_radix(&number, radix) {
+        Ok(i) => return Ok(sx_integer!(i)),
+        Err(_) => ()
+    }
+
+    // The digits are already known to be valid in the radix.
+    match BigInt::parse_bytes(number.as_bytes(), radix) {
+        Some(i) => return Ok(Sx::from_big_integer(i)),
+        None => {
+            assert!(false);
+            return Err(Error::InvalidToken(token.data.to_string(), token.span));
+        }
+    }
+}
+
+// Reads the character named after the backslash of a character literal:
+// a single grapheme, a name like space, or a code point like u{1F600}.
+fn read_char(name: &str) -> Option<String> {
+    match name {
+        "space"     => return Some(" ".to_string()),
+        "newline"   => return Some("\n".to_string()),
+        "tab"       => return Some("\t".to_string()),
+        "return"    => return Some("\r".to_string()),
+        _ if name.starts_with("u{") && name.ends_with('}') => {
+            return code_point(&name[2 .. name.len() - 1]).map(|c| c.to_string());
+        },
+        _ if name.graphemes(true).count() == 1 => return Some(name.to_string()),
+        _ => return None
+    }
+}
+
+// Reads a code point of one to six hexadecimal digits.
+fn code_point(code: &str) -> Option<char> {
+    let is_hex = code.chars().all(|c| c.is_ascii_hexdigit());
+    match code.len() {
+        1 ..= 6 if is_hex => return u32::from_str_radix(code, 16).ok().and_then(::std::char::from_u32),
+        _                 => return None
+    }
+}
+
+// Replaces escape sequences in string token data with the characters they
+// denote. On failure, returns the offset and text of the invalid escape.
+fn unescape(data: &str) -> Result<String, (usize, &str)> {
+    let mut s = String::with_capacity(data.len());
+    let mut chars = data.char_indices();
+
+    while let Some((_, c)) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+
+        let offset = chars.offset() - c.len_utf8();
+        match chars.next() {
+            Some((_, '"'))  => s.push('"'),
+            Some((_, '\\')) => s.push('\\'),
+            Some((_, 'n'))  => s.push('\n'),
+            Some((_, 't'))  => s.push('\t'),
+            Some((_, 'r'))  => s.push('\r'),
+            Some((_, '0'))  => s.push('\0'),
+
+            Some((_, 'u')) => {
+                let rest = &data[chars.offset() ..];
+                let code_end = match (rest.starts_with('{'), rest.find('}')) {
+                    (true, Some(code_end)) => code_end,
+                    _ => return Err((offset, &data[offset .. chars.offset()]))
+                };
+
+                let escape = &data[offset .. chars.offset() + code_end + 1];
+                match code_point(&rest[1 .. code_end]) {
+                    Some(c) => s.push(c),
+                    None    => return Err((offset, escape))
+                }
+
+                for _ in 0 .. code_end + 1 {
+                    chars.next();
                 }
+            },
 
-                sxs.push(sx);
-                opt_sx = None;
+            Some((_, _)) => {
+                return Err((offset, &data[offset .. chars.offset()]));
             },
 
-            None => ()
+            None => {
+                return Err((offset, &data[offset ..]));
+            }
+        }
+    }
+
+    return Ok(s);
+}
+
+// Removes the layout of a multi-line string: the line break right after the
+// opening quotes, the line of the closing quotes if it is blank, and the
+// indentation common to all lines that are not blank. Blank lines are
+// emptied and line breaks are read as newlines.
+fn dedent(data: &str) -> String {
+    let data = match data.starts_with("\r\n") {
+        true => &data[2 ..],
+        false if data.starts_with('\n') => &data[1 ..],
+        false => data
+    };
+
+    let mut lines = data.split('\n')
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>();
+
+    let is_blank = |line: &str| line.trim().is_empty();
+    let indent_size = |line: &str| line.chars().take_while(|c| c.is_whitespace()).count();
+
+    // The closing line sets the indentation even when it is blank.
+    let mut indent = match lines.last() {
+        Some(line) if lines.len() > 1 && is_blank(line) => indent_size(line),
+        _ => usize::MAX
+    };
+
+    if indent != usize::MAX {
+        lines.pop();
+    }
+
+    for line in lines.iter() {
+        if !is_blank(line) {
+            indent = indent.min(indent_size(line));
+        }
+    }
+
+    let mut s = String::with_capacity(data.len());
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            s.push('\n');
+        }
+
+        if !is_blank(line) {
+            s.extend(line.chars().skip(indent));
+        }
+    }
+
+    return s;
+}
+
+// Reader syntax preceding a form, applied once the form has been read.
+// Quasiquote and unquotes wrap the form in a list headed by the given symbol,
+// e.g. `x reads as (quasiquote x), and tags pass the form to their handler.
+enum Prefix {
+    Quote(Span),
+    Wrap(&'static str, Span),
+    Tag(String, Handler, Span),
+    Discard
+}
+
+// Applies pending prefixes to a form, innermost first, and pushes the result.
+// A datum comment, or a tag whose handler rejects the form, discards the form
+// and leaves the outer prefixes pending for the next form.
+fn push_form(forms: &mut Vec<Form>, mut form: Form, prefixes: &mut Vec<Prefix>, errors: &mut Vec<Error>) {
+    while let Some(prefix) = prefixes.pop() {
+        match prefix {
+            Prefix::Quote(quote_span) => {
+                form = Form {
+                    sx:         sx_quote!(form.sx.clone()),
+                    span:       Span::new(quote_span.start, form.span.end),
+                    children:   vec![form]
+                };
+            },
+
+            Prefix::Wrap(name, wrap_span) => {
+                form = Form {
+                    sx:         sx_list![sx_symbol!(name), form.sx.clone()],
+                    span:       Span::new(wrap_span.start, form.span.end),
+                    children:   vec![form]
+                };
+            },
+
+            Prefix::Tag(tag, handler, tag_span) => {
+                let span = Span::new(tag_span.start, form.span.end);
+                match handler(&form.sx) {
+                    Ok(sx) => {
+                        form = Form {
+                            sx,
+                            span,
+                            children:   vec![form]
+                        };
+                    },
+
+                    Err(message) => {
+                        errors.push(Error::InvalidTaggedLiteral(tag, message, span));
+                        return;
+                    }
+                }
+            },
+
+            Prefix::Discard => {
+                return;
+            }
         }
+    }
+
+    forms.push(form);
+}
 
+// Builds a map from alternating key and value forms, rejecting an odd
+// number of forms and repeated keys.
+fn read_map(forms: &[Form], span: Span) -> Result<Sx, Error> {
+    if !forms.len().is_multiple_of(2) {
+        return Err(Error::OddMapForms(span));
     }
 
-    for (_, top_delim, _) in read_stack.iter() {
-        read_errors.push(Error::UnmatchedDelimiter(*top_delim));
+    let mut m = im::OrdMap::new();
+    for entry in forms.chunks(2) {
+        let (key, value) = (&entry[0], &entry[1]);
+        if m.insert(key.sx.clone(), value.sx.clone()).is_some() {
+            let first = forms.iter().step_by(2).find(|first| first.sx == key.sx).unwrap();
+            return Err(Error::DuplicateMapKey(key.sx.to_string(), key.span, first.span));
+        }
     }
 
-    if !read_errors.is_empty() {
-        return Err(read_errors);
+    return Ok(Sx::Map(Arc::new(m)));
+}
+
+// Builds a set from element forms, rejecting repeated elements.
+fn read_set(forms: &[Form]) -> Result<Sx, Error> {
+    let mut set = im::HashSet::new();
+    for form in forms.iter() {
+        if set.insert(form.sx.clone()).is_some() {
+            let first = forms.iter().find(|first| first.sx == form.sx).unwrap();
+            return Err(Error::DuplicateSetElement(form.sx.to_string(), form.span, first.span));
+        }
     }
 
-    return Ok(sxs);
+    return Ok(Sx::Set(SxSet(Arc::new(set))));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use ::read::span::Position;
+    use ::sx::SxTaggedInfo;
+
+    fn parse(source: &str) -> read::FormResult {
+        return super::parse(source, &DispatchTable::new());
+    }
+
     fn test_sxs(source: &str, exp_sxs: Vec<Sx>) {
-        let act_sxs = parse(source);
+        let act_sxs = read::read(source);
         assert!(act_sxs.is_ok());
         assert_eq!(sx_list_from_vec!(exp_sxs).to_string(),
                    sx_list_from_vec!(act_sxs.unwrap()).to_string());
     }
 
+    fn span(start: (usize, usize, usize), end: (usize, usize, usize)) -> Span {
+        return Span::new(Position::new(start.0, start.1, start.2),
+                         Position::new(end.0, end.1, end.2));
+    }
+
     fn test_errors(source: &str, exp_errs: Vec<Error>) {
         let act_errs = parse(source);
         assert_eq!(Err(exp_errs), act_errs);
@@ -371,6 +810,144 @@ mod tests {
         test_sxs("[[nil] [foo [\"北京市\"]] [bar]]", exp_sxs);
     }
 
+    #[test]
+    fn test_map_empty() {
+        let exp_sxs = vec![
+            sx_map!{}
+        ];
+
+        test_sxs("{}", exp_sxs);
+    }
+
+    #[test]
+    fn test_map_nonempty() {
+        let exp_sxs = vec![
+            sx_map!{
+                sx_symbol!("foo") => sx_integer!(1),
+                sx_string!("北京市") => sx_vector![sx_nil!()]
+            }
+        ];
+
+        test_sxs("{foo 1 \"北京市\" [nil]}", exp_sxs);
+    }
+
+    #[test]
+    fn test_map_nested() {
+        let exp_sxs = vec![
+            sx_map!{
+                sx_map!{} => sx_map!{sx_integer!(1) => sx_integer!(2)},
+                sx_symbol!("bar") => sx_quote!(sx_symbol!("baz"))
+            }
+        ];
+
+        test_sxs("{{} {1 2} bar 'baz}", exp_sxs);
+    }
+
+    #[test]
+    fn test_set() {
+        let exp_sxs = vec![
+            sx_set![],
+            sx_set![
+                sx_integer!(1),
+                sx_symbol!("foo"),
+                sx_set![sx_string!("北京市")],
+                sx_map!{sx_integer!(2) => sx_integer!(3)}
+            ]
+        ];
+
+        test_sxs("#{} #{foo 1 #{\"北京市\"} {2 3}}", exp_sxs);
+    }
+
+    #[test]
+    fn test_set_equality() {
+        assert_eq!(read::read("#{a 1 [b]}"), read::read("#{[b] a 1}"));
+        assert_eq!(read::read("{#{a b} 1}"), read::read("{#{b a} 1}"));
+    }
+
+    #[test]
+    fn test_keyword() {
+        let exp_sxs = vec![
+            sx_keyword!("foo"),
+            sx_map!{
+                sx_keyword!("a") => sx_integer!(1),
+                sx_symbol!("a") => sx_keyword!("nil")
+            },
+            sx_quote!(sx_keyword!("bar"))
+        ];
+
+        test_sxs(":foo {:a 1 a :nil} ':bar", exp_sxs);
+        assert_ne!(read::read(":foo"), read::read("foo"));
+    }
+
+    #[test]
+    fn test_quasiquote() {
+        let exp_sxs = vec![
+            sx_list![
+                sx_symbol!("quasiquote"),
+                sx_list![
+                    sx_symbol!("a"),
+                    sx_list![sx_symbol!("unquote"), sx_symbol!("b")],
+                    sx_list![sx_symbol!("unquote-splicing"), sx_vector![sx_symbol!("c")]],
+                    sx_list![
+                        sx_symbol!("unquote"),
+                        sx_list![sx_symbol!("quasiquote"), sx_quote!(sx_symbol!("d"))]
+                    ]
+                ]
+            ]
+        ];
+
+        test_sxs("`(a ,b ,@[c] ,`'d)", exp_sxs);
+    }
+
+    #[test]
+    fn test_quasiquote_spans() {
+        let forms = parse("`(a ,b)").unwrap();
+        assert_eq!(span((0, 1, 1), (7, 1, 8)), forms[0].span);
+
+        let list = &forms[0].children[0];
+        assert_eq!(span((1, 1, 2), (7, 1, 8)), list.span);
+        assert_eq!(span((4, 1, 5), (6, 1, 7)), list.children[1].span);
+    }
+
+    #[test]
+    fn test_char() {
+        let exp_sxs = vec![
+            sx_char!("a"),
+            sx_char!(" "),
+            sx_char!("\n"),
+            sx_char!("\t"),
+            sx_char!("😀"),
+            sx_char!("e\u{301}"),
+            sx_char!("("),
+            sx_char!("u"),
+            sx_vector![sx_char!("]")]
+        ];
+
+        test_sxs("\\a \\space \\newline \\u{9} \\u{1F600} \\e\u{301} \\( \\u [\\]]", exp_sxs);
+    }
+
+    #[test]
+    fn test_char_round_trip() {
+        let source = "[\\a \\space \\newline \\tab \\return \\u{0} \\😀 \\e\u{301} \\\\ \\\"]";
+        assert_eq!(source, read::read(source).unwrap()[0].to_string());
+    }
+
+    #[test]
+    fn test_char_errors() {
+        let exp_errs = vec![
+            Error::InvalidChar("\\ab".to_string(), span((0, 1, 1), (3, 1, 4))),
+            Error::InvalidChar("\\u{110000}".to_string(), span((4, 1, 5), (14, 1, 15))),
+            Error::InvalidChar("\\u{}".to_string(), span((15, 1, 16), (19, 1, 20)))
+        ];
+
+        test_errors("\\ab \\u{110000} \\u{}", exp_errs);
+    }
+
+    #[test]
+    fn test_map_equality() {
+        assert_eq!(read::read("{a 1 b 2}"), read::read("{b 2 a 1}"));
+    }
+
     #[test]
     fn test_multi_flat() {
         let exp_sxs = vec![
@@ -470,26 +1047,110 @@ mod tests {
     #[test]
     fn test_invalid_tokens() {
         let exp_errs = vec![
-            Error::InvalidToken("bar,,,".to_string())
+            Error::InvalidToken("bar,,,".to_string(), span((4, 1, 5), (10, 1, 11)))
         ];
 
         test_errors("foo bar,,, baz", exp_errs);
     }
 
     #[test]
-    fn test_int_overflow() {
+    fn test_int_big() {
+        let big = "100200300400500600700800".parse::<BigInt>().unwrap();
+        let exp_sxs = vec![
+            Sx::from_big_integer(big.clone()),
+            Sx::from_big_integer(-big)
+        ];
+
+        test_sxs("100200300400500600700800 -100200300400500600700800", exp_sxs);
+    }
+
+    #[test]
+    fn test_int_limits() {
+        let exp_sxs = vec![
+            sx_integer!(i64::MAX),
+            sx_integer!(i64::MIN)
+        ];
+
+        assert_eq!(Ok(exp_sxs), read::read("9223372036854775807 -9223372036854775808"));
+
+        let sxs = read::read("9223372036854775808 -9223372036854775809").unwrap();
+        assert_eq!(vec!["9223372036854775808".to_string(), "-9223372036854775809".to_string()],
+                   sxs.iter().map(|sx| sx.to_string()).collect::<Vec<_>>());
+
+        match sxs[..] {
+            [Sx::BigInteger(_), Sx::BigInteger(_)] => (),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_int_radix() {
+        let exp_sxs = vec![
+            sx_integer!(255),
+            sx_integer!(-48865),
+            sx_integer!(493),
+            sx_integer!(10),
+            sx_integer!(-5),
+            sx_integer!(1000000),
+            sx_integer!(0xdead_beef),
+            sx_integer!(0)
+        ];
+
+        assert_eq!(Ok(exp_sxs), read::read("0xFF -0xbeE1 0o755 0b1010 -0b101 1_000_000 0xdead_beef -0x0"));
+    }
+
+    #[test]
+    fn test_int_radix_limits() {
+        let exp_sxs = vec![
+            sx_integer!(i64::MAX),
+            sx_integer!(i64::MIN),
+            sx_integer!(i64::MAX),
+            sx_integer!(i64::MIN),
+            sx_integer!(i64::MIN)
+        ];
+
+        let source = "0x7FFF_FFFF_FFFF_FFFF -0x8000000000000000 0o777777777777777777777 \
+                      -0o1000000000000000000000 -0b1000000000000000000000000000000000000000000000000000000000000000";
+        assert_eq!(Ok(exp_sxs), read::read(source));
+
+        let sxs = read::read("0xFFFF_FFFF_FFFF_FFFF -0x8000000000000001 0o1000000000000000000000 0b1_0000000000000000000000000000000000000000000000000000000000000000").unwrap();
+        assert_eq!(vec!["18446744073709551615", "-9223372036854775809", "9223372036854775808", "18446744073709551616"],
+                   sxs.iter().map(|sx| sx.to_string()).collect::<Vec<_>>());
+
+        match sxs[..] {
+            [Sx::BigInteger(_), Sx::BigInteger(_), Sx::BigInteger(_), Sx::BigInteger(_)] => (),
+            _ => assert!(false)
+        }
+    }
+
+    #[test]
+    fn test_int_radix_errors() {
         let exp_errs = vec![
-                Error::IntegerLimit("100200300400500600700800".to_string()),
-                Error::IntegerLimit("-100200300400500600700800".to_string())
+            Error::InvalidToken("0b102".to_string(), span((0, 1, 1), (5, 1, 6))),
+            Error::InvalidToken("0o8".to_string(), span((6, 1, 7), (9, 1, 10))),
+            Error::InvalidToken("0x".to_string(), span((10, 1, 11), (12, 1, 13))),
+            Error::InvalidToken("0x_1".to_string(), span((13, 1, 14), (17, 1, 18))),
+            Error::InvalidToken("1_".to_string(), span((18, 1, 19), (20, 1, 21))),
+            Error::InvalidToken("1__0".to_string(), span((21, 1, 22), (25, 1, 26)))
+        ];
+
+        test_errors("0b102 0o8 0x 0x_1 1_ 1__0", exp_errs);
+    }
+
+    #[test]
+    fn test_int_big_normalized() {
+        let exp_sxs = vec![
+            sx_integer!(0),
+            sx_integer!(42)
         ];
 
-        test_errors("100200300400500600700800 -100200300400500600700800", exp_errs);
+        assert_eq!(Ok(exp_sxs), read::read("-00000000000000000000000000 00000000000000000000000000042"));
     }
 
     #[test]
     fn test_partial_string() {
         let exp_errs = vec![
-                Error::PartialString("  ".to_string())
+                Error::PartialString("  ".to_string(), span((0, 1, 1), (3, 1, 4)))
         ];
 
         test_errors("\"  ", exp_errs);
@@ -498,8 +1159,8 @@ mod tests {
     #[test]
     fn test_invalid_close_delimiter() {
         let exp_errs = vec![
-            Error::InvalidCloseDelimiter(Kind::ListStart, "]".to_string()),
-            Error::InvalidCloseDelimiter(Kind::VectorStart, ")".to_string())
+            Error::InvalidCloseDelimiter(Kind::ListStart, "]".to_string(), span((12, 1, 13), (13, 1, 14)), span((0, 1, 1), (1, 1, 2))),
+            Error::InvalidCloseDelimiter(Kind::VectorStart, ")".to_string(), span((26, 1, 27), (27, 1, 28)), span((14, 1, 15), (15, 1, 16)))
         ];
 
         test_errors("(foo bar baz] [foo bar baz)", exp_errs);
@@ -508,19 +1169,344 @@ mod tests {
     #[test]
     fn test_unmatched_delimiter() {
         let exp_errs = vec![
-                Error::UnmatchedDelimiter(Kind::ListStart),
-                Error::UnmatchedDelimiter(Kind::VectorStart)
+                Error::UnmatchedDelimiter(Kind::ListStart, span((0, 1, 1), (1, 1, 2))),
+                Error::UnmatchedDelimiter(Kind::VectorStart, span((13, 1, 14), (14, 1, 15)))
         ];
 
         test_errors("(foo bar baz [foo bar baz", exp_errs);
     }
 
+    #[test]
+    fn test_map_errors() {
+        let exp_errs = vec![
+            Error::OddMapForms(span((0, 1, 1), (7, 1, 8))),
+            Error::DuplicateMapKey("a".to_string(), span((13, 1, 14), (14, 1, 15)), span((9, 1, 10), (10, 1, 11))),
+            Error::InvalidCloseDelimiter(Kind::MapStart, ")".to_string(), span((22, 1, 23), (23, 1, 24)), span((18, 1, 19), (19, 1, 20)))
+        ];
+
+        test_errors("{a 1 b} {a 1 a 2} {a 1)", exp_errs);
+    }
+
+    #[test]
+    fn test_set_errors() {
+        let exp_errs = vec![
+            Error::DuplicateSetElement("a".to_string(), span((4, 1, 5), (5, 1, 6)), span((2, 1, 3), (3, 1, 4))),
+            Error::InvalidCloseDelimiter(Kind::SetStart, "]".to_string(), span((12, 1, 13), (13, 1, 14)), span((7, 1, 8), (9, 1, 10))),
+            Error::UnmatchedDelimiter(Kind::SetStart, span((14, 1, 15), (16, 1, 17)))
+        ];
+
+        test_errors("#{a a} #{a b] #{", exp_errs);
+    }
+
     #[test]
     fn test_trailing_delimiter_list() {
         let exp_errs = vec![
-            Error::TrailingDelimiter(")".to_string())
+            Error::TrailingDelimiter(")".to_string(), span((13, 1, 14), (14, 1, 15)))
         ];
 
         test_errors("(foo bar baz))", exp_errs);
     }
+
+    #[test]
+    fn test_error_multiline() {
+        let exp_errs = vec![
+            Error::InvalidToken("bar,".to_string(), span((9, 2, 5), (13, 2, 9))),
+            Error::UnmatchedDelimiter(Kind::VectorStart, span((16, 4, 1), (17, 4, 2)))
+        ];
+
+        test_errors("(foo\n    bar,)\n\n[", exp_errs);
+    }
+
+    #[test]
+    fn test_error_to_string() {
+        let errs = parse("\n  (foo").unwrap_err();
+        assert_eq!(vec!["2:3: non-terminated list".to_string()],
+                   errs.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_form_spans() {
+        let forms = parse("foo\n  ('bar [1 \"北京\"])").unwrap();
+        assert_eq!(2, forms.len());
+        assert_eq!(span((0, 1, 1), (3, 1, 4)), forms[0].span);
+
+        let list = &forms[1];
+        assert_eq!(span((6, 2, 3), (25, 2, 18)), list.span);
+        assert_eq!(2, list.children.len());
+
+        let quote = &list.children[0];
+        assert_eq!(sx_quote!(sx_symbol!("bar")), quote.sx);
+        assert_eq!(span((7, 2, 4), (11, 2, 8)), quote.span);
+        assert_eq!(span((8, 2, 5), (11, 2, 8)), quote.children[0].span);
+
+        let vector = &list.children[1];
+        assert_eq!(span((12, 2, 9), (24, 2, 17)), vector.span);
+        assert_eq!(span((13, 2, 10), (14, 2, 11)), vector.children[0].span);
+        assert_eq!(span((15, 2, 12), (23, 2, 16)), vector.children[1].span);
+    }
+
+    #[test]
+    fn test_form_spans_quoted_list() {
+        let forms = parse(" ''(a)").unwrap();
+        assert_eq!(span((1, 1, 2), (6, 1, 7)), forms[0].span);
+        assert_eq!(span((2, 1, 3), (6, 1, 7)), forms[0].children[0].span);
+        assert_eq!(span((3, 1, 4), (6, 1, 7)), forms[0].children[0].children[0].span);
+    }
+
+    #[test]
+    fn test_line_comments() {
+        let exp_sxs = vec![
+            sx_list![
+                sx_symbol!("foo"),
+                sx_symbol!("bar")
+            ],
+            sx_symbol!("baz")
+        ];
+
+        test_sxs("; header\n(foo ; trailing )\n bar);(\nbaz ;", exp_sxs);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let exp_sxs = vec![
+            sx_vector![
+                sx_integer!(1),
+                sx_integer!(2)
+            ]
+        ];
+
+        test_sxs("#| (header |#[1 #| #| nested |# ] |# 2]#||#", exp_sxs);
+    }
+
+    #[test]
+    fn test_datum_comments() {
+        let exp_sxs = vec![
+            sx_symbol!("bar"),
+            sx_list![
+                sx_symbol!("a"),
+                sx_symbol!("d")
+            ],
+            sx_symbol!("c")
+        ];
+
+        test_sxs("#_foo bar (a #_(b c) d) #_#_a b c #_", exp_sxs);
+    }
+
+    #[test]
+    fn test_datum_comments_quoted() {
+        let exp_sxs = vec![
+            sx_quote!(sx_symbol!("b")),
+            sx_symbol!("d")
+        ];
+
+        test_sxs("'#_a b #_'c d", exp_sxs);
+    }
+
+    #[test]
+    fn test_partial_comment() {
+        let exp_errs = vec![
+            Error::PartialComment("#| a #| b |#".to_string(), span((4, 1, 5), (16, 1, 17)))
+        ];
+
+        test_errors("foo #| a #| b |#", exp_errs);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let exp_sxs = vec![
+            sx_string!("a\"b\\c\nd\te\rf\0gA😀"),
+            sx_string!("\\")
+        ];
+
+        test_sxs(r#""a\"b\\c\nd\te\rf\0g\u{41}\u{1F600}" "\\""#, exp_sxs);
+    }
+
+    #[test]
+    fn test_invalid_escapes() {
+        let exp_errs = vec![
+            Error::InvalidEscape(r"\q".to_string(), span((2, 1, 3), (4, 1, 5))),
+            Error::InvalidEscape(r"\u{}".to_string(), span((7, 1, 8), (11, 1, 12))),
+            Error::InvalidEscape(r"\u{110000}".to_string(), span((14, 1, 15), (24, 1, 25))),
+            Error::InvalidEscape(r"\u{D800}".to_string(), span((27, 1, 28), (35, 1, 36))),
+            Error::InvalidEscape(r"\u".to_string(), span((38, 1, 39), (40, 1, 41))),
+            Error::InvalidEscape(r"\u".to_string(), span((45, 1, 46), (47, 1, 48))),
+            Error::InvalidEscape(r"\u{+41}".to_string(), span((53, 1, 54), (60, 1, 61)))
+        ];
+
+        test_errors(r#"("\q" "\u{}" "\u{110000}" "\u{D800}" "\u41" "\u{12" "\u{+41}")"#, exp_errs);
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let strings = vec![
+            "",
+            "plain",
+            "北京市",
+            "\"quoted\"",
+            "back\\slash",
+            "line\nfeed\r\n",
+            "\ttab\0nul",
+            "\u{1b}[0m\u{7f}",
+            "\\\"\\n",
+            "\"#\"##",
+            "  indented\n\nlines\n",
+            "\n\"\"\"\\\"\n  \t",
+            "blank\n  \nline"
+        ];
+
+        for s in strings {
+            let sx = sx_string!(s);
+            assert_eq!(Ok(vec![sx.clone()]), read::read(&sx.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_float() {
+        let exp_sxs = vec![
+            sx_float!(1.5),
+            sx_float!(-0.25),
+            sx_float!(1e-9),
+            sx_float!(2500.0),
+            sx_float!(f64::INFINITY),
+            sx_float!(f64::NEG_INFINITY),
+            sx_float!(f64::NAN)
+        ];
+
+        test_sxs("1.5 -0.25 1e-9 2.5E+3 inf -inf nan", exp_sxs);
+    }
+
+    #[test]
+    fn test_float_equality() {
+        assert_eq!(sx_float!(f64::NAN), sx_float!(-f64::NAN));
+        assert_eq!(sx_float!(0.0), sx_float!(-0.0));
+        assert!(sx_float!(1.0) != sx_integer!(1));
+    }
+
+    #[test]
+    fn test_float_round_trip() {
+        let floats = vec![
+            0.0,
+            1.0,
+            -1.5,
+            0.1,
+            1e-9,
+            1e300,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN
+        ];
+
+        for f in floats {
+            let sx = sx_float!(f);
+            assert_eq!(Ok(vec![sx.clone()]), read::read(&sx.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_invalid_float() {
+        let exp_errs = vec![
+            Error::InvalidToken("1.2.3".to_string(), span((0, 1, 1), (5, 1, 6))),
+            Error::InvalidToken("1e".to_string(), span((6, 1, 7), (8, 1, 9)))
+        ];
+
+        test_errors("1.2.3 1e", exp_errs);
+    }
+
+    #[test]
+    fn test_raw_string() {
+        let exp_sxs = vec![
+            sx_string!(r"\d+\.\d*"),
+            sx_string!(r#"say "hi""#),
+            sx_string!("a\nb")
+        ];
+
+        test_sxs("#r\"\\d+\\.\\d*\" #r#\"say \"hi\"\"# #r\"a\nb\"", exp_sxs);
+
+        // Raw and multi-line strings print as escaped strings
+        let sx = sx_string!("say \"hi\"\\d\nb");
+        assert_eq!("\"say \\\"hi\\\"\\\\d\\nb\"", sx.to_string());
+        assert_eq!(Ok(vec![sx.clone()]), read::read(&sx.to_string()));
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        let exp_sxs = vec![
+            sx_string!("SELECT *\n  FROM t\n\nWHERE a = \"b\""),
+            sx_string!("a\n  b"),
+            sx_string!("a\n\tb"),
+            sx_string!("a\"\"b")
+        ];
+
+        test_sxs("\"\"\"
+            SELECT *
+              FROM t
+   \u{20}
+            WHERE a = \\\"b\"
+            \"\"\"
+          \"\"\"
+            a
+              b\"\"\"
+          \"\"\"a\\n\\tb\"\"\"
+          \"\"\"a\"\"b\"\"\"", exp_sxs);
+    }
+
+    #[test]
+    fn test_multi_line_string_errors() {
+        let exp_errs = vec![
+            Error::InvalidEscape(r"\q".to_string(), span((9, 2, 6), (11, 2, 8))),
+            Error::PartialString("\n\"\"".to_string(), span((16, 3, 5), (22, 4, 3)))
+        ];
+
+        test_errors("\"\"\"\n    a\\q\n\"\"\" \"\"\"\n\"\"", exp_errs);
+    }
+
+    #[test]
+    fn test_tagged() {
+        let exp_sxs = vec![
+            sx_tagged!("inst", sx_string!("2018-11-05T13:15:30Z")),
+            sx_vector![sx_tagged!("uuid", sx_string!("f81d4fae-7dec-11d0-a765-00a0c91e6bf6"))],
+            sx_quote!(sx_tagged!("inst", sx_string!("2018-11-05")))
+        ];
+
+        test_sxs("#inst \"2018-11-05t13:15:30z\" [#uuid\"F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6\"] '#inst #_ 1 \"2018-11-05\"", exp_sxs);
+    }
+
+    #[test]
+    fn test_tagged_round_trip() {
+        let source = "[#inst \"2018-11-05\" #uuid \"f81d4fae-7dec-11d0-a765-00a0c91e6bf6\"]";
+        assert_eq!(source, read::read(source).unwrap()[0].to_string());
+    }
+
+    #[test]
+    fn test_tagged_errors() {
+        let exp_errs = vec![
+            Error::UnknownTag("#point".to_string(), span((0, 1, 1), (6, 1, 7))),
+            Error::InvalidTaggedLiteral("inst".to_string(),
+                                        "expected an RFC 3339 timestamp, got \"2018-02-29\"".to_string(),
+                                        span((13, 1, 14), (31, 1, 32))),
+            Error::InvalidTaggedLiteral("uuid".to_string(),
+                                        "expected a string, got 1".to_string(),
+                                        span((33, 1, 34), (40, 1, 41)))
+        ];
+
+        test_errors("#point [1 2] #inst \"2018-02-29\" (#uuid 1)", exp_errs);
+        assert_eq!("1:14: invalid #inst literal: expected an RFC 3339 timestamp, got \"2018-02-29\"",
+                   parse("#point [1 2] #inst \"2018-02-29\"").unwrap_err()[1].to_string());
+    }
+
+    #[test]
+    fn test_tagged_handler() {
+        let mut dispatch = DispatchTable::new();
+        dispatch.register("double", |value| {
+            match value {
+                Sx::Integer(i) => Ok(sx_integer!(i * 2)),
+                _ => Err("expected an integer".to_string())
+            }
+        });
+
+        let forms = super::parse("(#double 21 #double #double 1)", &dispatch).unwrap();
+        assert_eq!("(42 4)", forms[0].sx.to_string());
+        assert_eq!(span((1, 1, 2), (11, 1, 12)), forms[0].children[0].span);
+        assert_eq!(sx_integer!(21), forms[0].children[0].children[0].sx);
+    }
 }
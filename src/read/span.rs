@@ -0,0 +1,120 @@
+use std::fmt;
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line:   usize,
+    pub column: usize
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug, Default)]
+pub struct Span {
+    pub start:  Position,
+    pub end:    Position
+}
+
+impl Position {
+    pub fn new(offset: usize, line: usize, column: usize) -> Position {
+        return Position {
+            offset,
+            line,
+            column
+        }
+    }
+
+    pub fn start() -> Position {
+        return Position::new(0, 1, 1);
+    }
+
+    // Returns the position right after reading the given text from this position.
+    // Columns count characters, so UTF-8 continuation bytes are skipped.
+    pub fn advance(&self, text: &str) -> Position {
+        let mut position = *self;
+        for &b in text.as_bytes().iter() {
+            position.offset += 1;
+            match b {
+                b'\n' => {
+                    position.line += 1;
+                    position.column = 1;
+                },
+
+                _ if b & 0xC0 != 0x80 => {
+                    position.column += 1;
+                },
+
+                _ => {}
+            }
+        }
+
+        return position;
+    }
+
+    // Returns the position right after reading the given character.
+    pub fn advance_char(&self, c: char) -> Position {
+        match c {
+            '\n' => return Position::new(self.offset + 1, self.line + 1, 1),
+            _ => return Position::new(self.offset + c.len_utf8(), self.line, self.column + 1)
+        }
+    }
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        return Span {
+            start,
+            end
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_empty() {
+        let exp = Position::new(0, 1, 1);
+        let act = Position::start().advance("");
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn test_advance_line() {
+        let exp = Position::new(6, 1, 7);
+        let act = Position::start().advance("(foo )");
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn test_advance_lines() {
+        let exp = Position::new(8, 3, 4);
+        let act = Position::start().advance("foo\n\nbar");
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn test_advance_unicode() {
+        let exp = Position::new(10, 2, 4);
+        let act = Position::start().advance("\n北京市");
+        assert_eq!(exp, act);
+    }
+
+    #[test]
+    fn test_advance_char() {
+        let text = "a\n北京\r\n🙂";
+        let act = text.chars().fold(Position::start(), |position, c| position.advance_char(c));
+        assert_eq!(Position::start().advance(text), act);
+    }
+}
@@ -0,0 +1,198 @@
+use ::read::FormResult;
+use ::read::dispatch::DispatchTable;
+use ::read::parse::{parse_at, Parser};
+use ::read::span::Position;
+use ::read::token::{Kind, TokenReader};
+
+// Reads forms from input that arrives in chunks, such as lines typed into
+// the REPL or read from a pipe. Input is consumed a line at a time, so that
+// a form is only read once the line it ends on is complete.
+pub struct Reader {
+    buffer:     String,
+    position:   Position,
+
+    // Forms left open by the lines read so far, and where those lines end,
+    // so that each line is read once however many lines a form spans.
+    parser:     Parser,
+    scan_end:   Position
+}
+
+impl Default for Reader {
+    fn default() -> Reader {
+        return Reader::new();
+    }
+}
+
+impl Reader {
+    pub fn new() -> Reader {
+        return Reader {
+            buffer:     String::new(),
+            position:   Position::start(),
+            parser:     Parser::new(Position::start()),
+            scan_end:   Position::start()
+        }
+    }
+
+    // Appends a chunk of input and returns the forms completed by it.
+    // Open lists, vectors, maps, sets, strings and block comments are kept
+    // for the next chunk. On a read error, all complete lines read so far
+    // are discarded. Tagged literals are read with the handlers of the given
+    // table, which may change between chunks.
+    pub fn feed(&mut self, chunk: &str, dispatch: &DispatchTable) -> FormResult {
+        self.buffer.push_str(chunk);
+
+        let line_size = match chunk.rfind('\n') {
+            Some(newline_offset) => self.buffer.len() - chunk.len() + newline_offset + 1,
+            None => return Ok(Vec::new())
+        };
+
+        // Strings and block comments left open are read again along with
+        // the next line, which may close them.
+        let scan_start = self.scan_end;
+        let lines = &self.buffer[scan_start.offset - self.position.offset .. line_size];
+        self.scan_end = scan_start.advance(lines);
+        for token in TokenReader::new(lines, scan_start) {
+            match token.kind {
+                Kind::StringPartial | Kind::BlockCommentPartial => {
+                    self.scan_end = token.span.start;
+                    break;
+                },
+
+                _ => self.parser.push(&token, dispatch)
+            }
+        }
+
+        let errors = self.parser.take_errors();
+        if !errors.is_empty() {
+            self.consume(line_size);
+            return Err(errors);
+        }
+
+        let forms = self.parser.take_forms();
+        let complete_size = self.parser.complete_end().offset - self.position.offset;
+        self.consume(complete_size);
+        return Ok(forms);
+    }
+
+    // Returns whether the input fed so far ends in the middle of a form.
+    pub fn is_incomplete(&self) -> bool {
+        return !self.buffer.trim().is_empty();
+    }
+
+    // Reads the remaining input once there is no more to come, reporting
+    // incomplete forms as errors.
+    pub fn finish(&mut self, dispatch: &DispatchTable) -> FormResult {
+        let result = parse_at(&self.buffer, self.position, dispatch);
+        let size = self.buffer.len();
+        self.consume(size);
+        return result;
+    }
+
+    // The parser starts over once all the input it has read is consumed.
+    fn consume(&mut self, size: usize) {
+        self.position = self.position.advance(&self.buffer[.. size]);
+        self.buffer.drain(.. size);
+
+        if self.scan_end.offset <= self.position.offset {
+            self.parser = Parser::new(self.position);
+            self.scan_end = self.position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ::read::{Error, Form};
+    use ::read::span::Span;
+    use ::read::token::Kind;
+    use ::sx::Sx;
+
+    fn sxs(forms: Vec<Form>) -> Vec<String> {
+        return forms.iter().map(|form| form.sx.to_string()).collect();
+    }
+
+    #[test]
+    fn test_complete_lines() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(vec!["1", "(foo bar)"], sxs(reader.feed("1 (foo bar)\n", &dispatch).unwrap()));
+        assert_eq!(vec!["[2]"], sxs(reader.feed("[2] ; comment\n", &dispatch).unwrap()));
+        assert!(!reader.is_incomplete());
+        assert_eq!(Ok(vec![]), reader.finish(&dispatch));
+    }
+
+    #[test]
+    fn test_incomplete_forms() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(vec!["1"], sxs(reader.feed("1 (foo\n", &dispatch).unwrap()));
+        assert!(reader.is_incomplete());
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("  [bar \"a\n", &dispatch).unwrap()));
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("b\" #| x\n", &dispatch).unwrap()));
+        assert_eq!(vec!["(foo [bar \"a\\nb\"])", "'baz"], sxs(reader.feed("|#]) '\nbaz\n", &dispatch).unwrap()));
+        assert!(!reader.is_incomplete());
+    }
+
+    #[test]
+    fn test_partial_lines() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("(foo) ba", &dispatch).unwrap()));
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("r ; x", &dispatch).unwrap()));
+        assert_eq!(vec!["(foo)", "bar"], sxs(reader.feed(" y\n", &dispatch).unwrap()));
+    }
+
+    #[test]
+    fn test_long_form() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("(\"a\n", &dispatch).unwrap()));
+        for _ in 0 .. 1000 {
+            assert_eq!(Vec::<String>::new(), sxs(reader.feed("\" 1 \"\n", &dispatch).unwrap()));
+        }
+
+        let forms = reader.feed("b\") 2\n", &dispatch).unwrap();
+        assert_eq!(2, forms.len());
+        assert_eq!(2001, forms[0].children.len());
+        assert_eq!(Span::new(Position::new(6008, 1002, 5), Position::new(6009, 1002, 6)), forms[1].span);
+    }
+
+    #[test]
+    fn test_spans() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        reader.feed("(a\n", &dispatch).unwrap();
+        let forms = reader.feed(" b) c\n", &dispatch).unwrap();
+        assert_eq!(Span::new(Position::new(0, 1, 1), Position::new(6, 2, 4)), forms[0].span);
+        assert_eq!(Span::new(Position::new(7, 2, 5), Position::new(8, 2, 6)), forms[1].span);
+    }
+
+    #[test]
+    fn test_errors() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(vec!["1:4: trailing delimiter: ')'"],
+                   reader.feed("(a)) (b\n", &dispatch)
+                         .unwrap_err()
+                         .iter()
+                         .map(|error| error.to_string())
+                         .collect::<Vec<_>>());
+
+        assert!(!reader.is_incomplete());
+        assert_eq!(vec!["c"], sxs(reader.feed("c\n", &dispatch).unwrap()));
+    }
+
+    #[test]
+    fn test_finish() {
+        let dispatch = DispatchTable::new();
+        let mut reader = Reader::new();
+        assert_eq!(Vec::<String>::new(), sxs(reader.feed("(a\n", &dispatch).unwrap()));
+        assert_eq!(Err(vec![Error::UnmatchedDelimiter(Kind::ListStart, Span::new(Position::new(0, 1, 1), Position::new(1, 1, 2)))]),
+                   reader.finish(&dispatch));
+
+        assert_eq!(Ok(vec![]), reader.feed("nil", &dispatch));
+        assert_eq!(vec![Sx::Nil], reader.finish(&dispatch).unwrap().into_iter().map(|form| form.sx).collect::<Vec<_>>());
+    }
+}
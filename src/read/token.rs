@@ -1,4 +1,6 @@
-use unicode_segmentation::UnicodeSegmentation;
+use unicode_xid::UnicodeXID;
+
+use ::read::span::{Position, Span};
 
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Kind {
@@ -7,14 +9,42 @@ pub enum Kind {
     Nil,
     Boolean,
     Integer,
+    Float,
     Symbol,
+    Keyword,
+    Char,
     StringPartial,
     String,
+    RawString,
+    MultiLineString,
     ListStart,
     ListEnd,
     VectorStart,
     VectorEnd,
+    MapStart,
+    MapEnd,
+    SetStart,
     Quote,
+    Quasiquote,
+    Unquote,
+    UnquoteSplicing,
+    Hash,
+    Tag,
+    LineComment,
+    BlockCommentPartial,
+    BlockComment,
+    DatumComment,
+
+    // Whitespace is skipped by the tokenizer and only kept in concrete
+    // syntax trees.
+    Whitespace,
+
+    // Intermediate states of raw and multi-line strings, which end up as
+    // other tokens when their input ends.
+    RawStringStart,
+    RawStringPartial,
+    MultiLineStringStart,
+    MultiLineStringPartial,
 
     Invalid
 }
@@ -23,7 +53,8 @@ pub enum Kind {
 pub struct Token<'a> {
     pub kind: Kind,
     pub size: usize,
-    pub data: &'a str
+    pub data: &'a str,
+    pub span: Span
 }
 
 impl <'a> Token<'a> {
@@ -31,7 +62,8 @@ impl <'a> Token<'a> {
         return Token {
             kind,
             size: 0,
-            data
+            data,
+            span: Span::default()
         }
     }
 
@@ -44,27 +76,58 @@ impl <'a> Token<'a> {
     }
 }
 
-struct TokenReader<'a> {
-    window: &'a str
+// Reads tokens one at a time, so that large sources can be read without
+// holding all of their tokens at once.
+pub struct TokenReader<'a> {
+    window:     &'a str,
+    position:   Position
 }
 
 impl <'a> TokenReader<'a> {
-    fn new(source: &'a str) -> TokenReader<'a> {
+    pub fn new(source: &'a str, start: Position) -> TokenReader<'a> {
         return TokenReader {
-            window: source
+            window:     source,
+            position:   start
         }
     }
 
-    fn next(&mut self) -> Token<'a> {
+    fn read_token(&mut self) -> Token<'a> {
         let mut token = Token::new(Kind::Empty, self.window);
+        let mut token_offset = 0;
+        let mut comment_depth = 0;
+        let mut prev_c = '\0';
+        let mut escaped = false;
+
+        // Raw strings close with a quote and as many hashes as they opened
+        // with, and multi-line strings close with three unescaped quotes.
+        let mut hash_count = 0;
+        let mut close_count = None;
+        let mut quote_count = 0;
+
+        // Sizes of the string delimiters, which are not part of the token data.
+        let mut open_size = 0;
+        let mut close_size = 0;
+
+        // Positions are advanced along with the scan. A token and the text
+        // read end either right after the last character scanned, or right
+        // before it when it is a delimiter or whitespace that ends the token.
+        let mut start = self.position;
+        let mut prev_position = self.position;
+        let mut position = self.position;
+        let mut last_size = 0;
 
         let mut read_size = 0;
-        for (_, (offset, s)) in self.window.grapheme_indices(true).enumerate() {
-            let c = s.chars().next().unwrap();
-
-            // TODO: fix this hack;
+        for (offset, c) in self.window.char_indices() {
             read_size += c.len_utf8();
 
+            prev_position = position;
+            position = position.advance_char(c);
+            last_size = c.len_utf8();
+
+            if token.kind == Kind::Empty {
+                start = prev_position;
+            }
+
             match token.kind {
                 Kind::Empty => {
                     match c {
@@ -75,23 +138,42 @@ impl <'a> TokenReader<'a> {
 
                         // Empty -> Integer
                         _ if c.is_ascii_digit() => {
+                            token_offset = offset;
                             token = Token::new(Kind::Integer, &self.window[offset ..]);
                             token.update(c);
                         }
 
                         // Empty -> Symbol
                         _ if is_symbol_start(c) => {
+                            token_offset = offset;
                             token = Token::new(Kind::Symbol, &self.window[offset ..]);
                             token.update(c);
                         },
 
+                        // Empty -> Keyword
+                        ':' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::Keyword, &self.window[offset ..]);
+                            token.update(c);
+                        },
+
+                        // Empty -> Char
+                        '\\' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::Char, &self.window[offset ..]);
+                            token.update(c);
+                        },
+
                         // Empty -> String
                         '"' => {
-                            token = Token::new(Kind::StringPartial, &self.window[offset ..]);
+                            token_offset = offset;
+                            token = Token::new(Kind::StringPartial, &self.window[read_size ..]);
+                            open_size = 1;
                         }
 
                         // Empty -> Done (ListStart)
                         '(' => {
+                            token_offset = offset;
                             token = Token::new(Kind::ListStart, &self.window[offset ..]);
                             token.update(c);
                             break;
@@ -99,6 +181,7 @@ impl <'a> TokenReader<'a> {
 
                         // Empty -> Done (ListEnd)
                         ')' => {
+                            token_offset = offset;
                             token = Token::new(Kind::ListEnd, &self.window[offset ..]);
                             token.update(c);
                             break;
@@ -106,6 +189,7 @@ impl <'a> TokenReader<'a> {
 
                         // Empty -> Done (VectorStart)
                         '[' => {
+                            token_offset = offset;
                             token = Token::new(Kind::VectorStart, &self.window[offset ..]);
                             token.update(c);
                             break;
@@ -113,26 +197,190 @@ impl <'a> TokenReader<'a> {
 
                         // Empty -> Done (VectorEnd)
                         ']' => {
+                            token_offset = offset;
                             token = Token::new(Kind::VectorEnd, &self.window[offset ..]);
                             token.update(c);
                             break;
                         }
 
+                        // Empty -> Done (MapStart)
+                        '{' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::MapStart, &self.window[offset ..]);
+                            token.update(c);
+                            break;
+                        }
+
+                        // Empty -> Done (MapEnd)
+                        '}' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::MapEnd, &self.window[offset ..]);
+                            token.update(c);
+                            break;
+                        }
+
                         // Empty -> Done (Quote)
                         '\'' => {
+                            token_offset = offset;
                             token = Token::new(Kind::Quote, &self.window[offset ..]);
                             token.update(c);
                             break;
                         }
 
+                        // Empty -> Done (Quasiquote)
+                        '`' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::Quasiquote, &self.window[offset ..]);
+                            token.update(c);
+                            break;
+                        }
+
+                        // Empty -> Unquote
+                        ',' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::Unquote, &self.window[offset ..]);
+                            token.update(c);
+                        }
+
+                        // Empty -> LineComment
+                        ';' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::LineComment, &self.window[offset ..]);
+                            token.update(c);
+                        }
+
+                        // Empty -> Hash
+                        '#' => {
+                            token_offset = offset;
+                            token = Token::new(Kind::Hash, &self.window[offset ..]);
+                            token.update(c);
+                        }
+
                         // Empty -> Invalid
                         _ => {
+                            token_offset = offset;
                             token = Token::new(Kind::Invalid, &self.window[offset ..]);
                             token.update(c);
                         }
                     }
                 },
 
+                Kind::Unquote => {
+                    match c {
+                        // Unquote -> Done (UnquoteSplicing)
+                        '@' => {
+                            token.kind = Kind::UnquoteSplicing;
+                            token.update(c);
+                            break;
+                        }
+
+                        // Unquote -> Done
+                        _ => {
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+                    }
+                },
+
+                Kind::Hash => {
+                    match c {
+                        // Hash -> BlockCommentPartial
+                        '|' => {
+                            token.kind = Kind::BlockCommentPartial;
+                            token.update(c);
+                            comment_depth = 1;
+                        }
+
+                        // Hash -> Done (SetStart)
+                        '{' => {
+                            token.kind = Kind::SetStart;
+                            token.update(c);
+                            break;
+                        }
+
+                        // Hash -> RawStringStart
+                        'r' => {
+                            token.kind = Kind::RawStringStart;
+                            token.update(c);
+                        }
+
+                        // Hash -> Done (DatumComment)
+                        '_' => {
+                            token.kind = Kind::DatumComment;
+                            token.update(c);
+                            break;
+                        }
+
+                        // Hash -> Tag
+                        _ if is_symbol_start(c) => {
+                            token.kind = Kind::Tag;
+                            token.update(c);
+                        }
+
+                        // Hash -> Done (Invalid)
+                        _ if c.is_whitespace() => {
+                            token.kind = Kind::Invalid;
+                            break;
+                        }
+
+                        // Hash -> Done (Invalid)
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '}' | ';' => {
+                            token.kind = Kind::Invalid;
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+
+                        // Hash -> Invalid
+                        _ => {
+                            token.kind = Kind::Invalid;
+                            token.update(c);
+                        }
+                    }
+                },
+
+                Kind::LineComment => {
+                    match c {
+                        // LineComment -> Done
+                        '\n' | '\r' => {
+                            break;
+                        }
+
+                        // LineComment -> LineComment
+                        _ => {
+                            token.update(c);
+                        }
+                    }
+                },
+
+                Kind::BlockCommentPartial => {
+                    token.update(c);
+
+                    match c {
+                        // BlockCommentPartial -> BlockCommentPartial (nested)
+                        '|' if prev_c == '#' => {
+                            comment_depth += 1;
+                            prev_c = '\0';
+                        }
+
+                        // BlockCommentPartial -> Done (BlockComment)
+                        '#' if prev_c == '|' && comment_depth == 1 => {
+                            token.kind = Kind::BlockComment;
+                            break;
+                        }
+
+                        // BlockCommentPartial -> BlockCommentPartial (unnested)
+                        '#' if prev_c == '|' => {
+                            comment_depth -= 1;
+                            prev_c = '\0';
+                        }
+
+                        // BlockCommentPartial -> BlockCommentPartial
+                        _ => {
+                            prev_c = c;
+                        }
+                    }
+                },
+
                 Kind::Integer => {
                     match c {
                         // Integer -> Done
@@ -141,7 +389,7 @@ impl <'a> TokenReader<'a> {
                         }
 
                         // Integer -> Done
-                        '(' | ')' | '"' | '\'' | '[' | ']' => {
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
                             read_size -= c.len_utf8();
                             break;
                         }
@@ -151,6 +399,27 @@ impl <'a> TokenReader<'a> {
                             token.update(c);
                         }
 
+                        // Integer -> Integer (radix prefix)
+                        'x' | 'o' | 'b' if is_zero(&token.data[.. token.size]) => {
+                            token.update(c);
+                        }
+
+                        // Integer -> Integer (hexadecimal digit)
+                        _ if c.is_ascii_hexdigit() && is_hex(&token.data[.. token.size]) => {
+                            token.update(c);
+                        }
+
+                        // Integer -> Integer (digit separator)
+                        '_' => {
+                            token.update(c);
+                        }
+
+                        // Integer -> Float
+                        '.' | 'e' | 'E' => {
+                            token.kind = Kind::Float;
+                            token.update(c);
+                        }
+
                         // Integer -> Invalid
                         _ => {
                             token.kind = Kind::Invalid;
@@ -159,6 +428,32 @@ impl <'a> TokenReader<'a> {
                     }
                 },
 
+                Kind::Float => {
+                    match c {
+                        // Float -> Done
+                        _ if c.is_whitespace() => {
+                            break;
+                        }
+
+                        // Float -> Done
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+
+                        // Float -> Float
+                        _ if c.is_ascii_digit() || ".eE+-".contains(c) => {
+                            token.update(c);
+                        }
+
+                        // Float -> Invalid
+                        _ => {
+                            token.kind = Kind::Invalid;
+                            token.update(c);
+                        }
+                    }
+                },
+
                 Kind::Symbol => {
                     match c {
                         // Symbol -> Done
@@ -167,7 +462,7 @@ impl <'a> TokenReader<'a> {
                         },
 
                         // Symbol -> Done
-                        '(' | ')' | '"' | '\'' | '[' | ']' => {
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
                             read_size -= c.len_utf8();
                             break;
                         },
@@ -191,7 +486,7 @@ impl <'a> TokenReader<'a> {
                         },
 
                         // Symbol -> Integer
-                        _ if token.size == 1 && token.data.starts_with("-") => {
+                        _ if token.size == 1 && token.data.starts_with("-") && c.is_ascii_digit() => {
                             token.kind = Kind::Integer;
                             token.update(c);
                         }
@@ -209,27 +504,226 @@ impl <'a> TokenReader<'a> {
                     }
                 },
 
+                Kind::Char => {
+                    match c {
+                        // Char -> Char (the character itself, which may be a delimiter)
+                        _ if token.size == 1 && !c.is_whitespace() => {
+                            token.update(c);
+                        },
+
+                        // Char -> Char (start of code point)
+                        '{' if &token.data[.. token.size] == "\\u" => {
+                            token.update(c);
+                        },
+
+                        // Char -> Done (end of code point)
+                        '}' if token.data[.. token.size].starts_with("\\u{") => {
+                            token.update(c);
+                            break;
+                        },
+
+                        // Char -> Done
+                        _ if c.is_whitespace() => {
+                            break;
+                        },
+
+                        // Char -> Done
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' | '\\' => {
+                            read_size -= c.len_utf8();
+                            break;
+                        },
+
+                        // Char -> Char
+                        _ => {
+                            token.update(c);
+                        }
+                    }
+                },
+
+                Kind::Keyword => {
+                    match c {
+                        // Keyword -> Done
+                        _ if c.is_whitespace() => {
+                            break;
+                        },
+
+                        // Keyword -> Done
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
+                            read_size -= c.len_utf8();
+                            break;
+                        },
+
+                        // Keyword -> Keyword
+                        _ if is_symbol(c) => {
+                            token.update(c);
+                        },
+
+                        // Keyword -> Invalid
+                        _ => {
+                            token.kind = Kind::Invalid;
+                            token.update(c);
+                        }
+                    }
+                },
+
                 Kind::StringPartial => {
                     match c {
+                        // StringPartial -> MultiLineStringStart
+                        '"' if !escaped && token.size == 0 => {
+                            token.kind = Kind::MultiLineStringStart;
+                        }
+
                         // StringPartial -> Done (String)
-                        '"' => {
+                        '"' if !escaped => {
                             token.kind = Kind::String;
+                            close_size = 1;
                             break;
                         }
 
                         // String -> String
-                        _ if token.size == 0 => {
-                            token.data = &self.window[offset ..];
+                        _ => {
                             token.update(c);
+                            escaped = !escaped && c == '\\';
                         }
+                    }
+                },
 
-                        // String -> String
+                Kind::MultiLineStringStart => {
+                    match c {
+                        // MultiLineStringStart -> MultiLineStringPartial
+                        '"' => {
+                            token.kind = Kind::MultiLineStringPartial;
+                            token.data = &self.window[read_size ..];
+                            open_size = 3;
+                        }
+
+                        // MultiLineStringStart -> Done (String)
+                        _ => {
+                            token.kind = Kind::String;
+                            close_size = 1;
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+                    }
+                },
+
+                Kind::MultiLineStringPartial => {
+                    token.update(c);
+                    quote_count = match c {
+                        '"' if !escaped => quote_count + 1,
+                        _ => 0
+                    };
+
+                    escaped = !escaped && c == '\\';
+
+                    // MultiLineStringPartial -> Done (MultiLineString)
+                    if quote_count == 3 {
+                        token.kind = Kind::MultiLineString;
+                        token.size -= 3;
+                        close_size = 3;
+                        break;
+                    }
+                },
+
+                Kind::RawStringStart => {
+                    match c {
+                        // RawStringStart -> RawStringStart
+                        '#' => {
+                            token.update(c);
+                            hash_count += 1;
+                        }
+
+                        // RawStringStart -> RawStringPartial
+                        '"' => {
+                            token.kind = Kind::RawStringPartial;
+                            token.data = &self.window[read_size ..];
+                            token.size = 0;
+                            open_size = read_size - token_offset;
+                        }
+
+                        // RawStringStart -> Tag
+                        _ if hash_count == 0 && is_symbol(c) => {
+                            token.kind = Kind::Tag;
+                            token.update(c);
+                        }
+
+                        // RawStringStart -> Done (Tag)
+                        _ if hash_count == 0 && c.is_whitespace() => {
+                            token.kind = Kind::Tag;
+                            break;
+                        }
+
+                        // RawStringStart -> Done (Tag)
+                        '(' | ')' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' if hash_count == 0 => {
+                            token.kind = Kind::Tag;
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+
+                        // RawStringStart -> Done (Invalid)
+                        _ if c.is_whitespace() => {
+                            token.kind = Kind::Invalid;
+                            break;
+                        }
+
+                        // RawStringStart -> Done (Invalid)
+                        '(' | ')' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
+                            token.kind = Kind::Invalid;
+                            read_size -= c.len_utf8();
+                            break;
+                        }
+
+                        // RawStringStart -> Invalid
+                        _ => {
+                            token.kind = Kind::Invalid;
+                            token.update(c);
+                        }
+                    }
+                },
+
+                Kind::Tag => {
+                    match c {
+                        // Tag -> Done
+                        _ if c.is_whitespace() => {
+                            break;
+                        },
+
+                        // Tag -> Done
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
+                            read_size -= c.len_utf8();
+                            break;
+                        },
+
+                        // Tag -> Tag
+                        _ if is_symbol(c) => {
+                            token.update(c);
+                        },
+
+                        // Tag -> Invalid
                         _ => {
+                            token.kind = Kind::Invalid;
                             token.update(c);
                         }
                     }
                 },
 
+                Kind::RawStringPartial => {
+                    token.update(c);
+                    close_count = match (c, close_count) {
+                        ('"', _) => Some(0),
+                        ('#', Some(count)) => Some(count + 1),
+                        _ => None
+                    };
+
+                    // RawStringPartial -> Done (RawString)
+                    if close_count == Some(hash_count) {
+                        token.kind = Kind::RawString;
+                        token.size -= hash_count + 1;
+                        close_size = hash_count + 1;
+                        break;
+                    }
+                },
+
                 Kind::Nil => {
                     match c {
                         // Nil -> Done
@@ -238,7 +732,7 @@ impl <'a> TokenReader<'a> {
                         },
 
                         // Nil -> Done
-                        '(' | ')' | '"' | '\'' | '[' | ']' => {
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
                             read_size -= c.len_utf8();
                             break;
                         },
@@ -265,7 +759,7 @@ impl <'a> TokenReader<'a> {
                         },
 
                         // Boolean -> Done
-                        '(' | ')' | '"' | '\'' | '[' | ']' => {
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
                             read_size -= c.len_utf8();
                             break;
                         },
@@ -292,7 +786,7 @@ impl <'a> TokenReader<'a> {
                         }
 
                         // Invalid -> Done
-                        '(' | ')' | '"' | '\'' | '[' | ']' => {
+                        '(' | ')' | '"' | '\'' | '`' | '[' | ']' | '{' | '}' | ';' => {
                             read_size -= c.len_utf8();
                             break;
                         },
@@ -310,48 +804,146 @@ impl <'a> TokenReader<'a> {
             }
         }
 
-        self.window = &self.window[read_size ..];
+        // A lone hash at the end of input
+        if token.kind == Kind::Hash {
+            token.kind = Kind::Invalid;
+        }
+
+        // A raw string prefix, or a tag named r, at the end of input
+        if token.kind == Kind::RawStringStart {
+            token.kind = match hash_count {
+                0 => Kind::Tag,
+                _ => Kind::Invalid
+            };
+        }
+
+        // An empty string at the end of input
+        if token.kind == Kind::MultiLineStringStart {
+            token.kind = Kind::String;
+            close_size = 1;
+        }
+
+        // An unterminated raw or multi-line string
+        if token.kind == Kind::RawStringPartial || token.kind == Kind::MultiLineStringPartial {
+            token.kind = Kind::StringPartial;
+        }
+
+        // A lone backslash
+        if token.kind == Kind::Char && token.size == 1 {
+            token.kind = Kind::Invalid;
+        }
+
+        // A lone colon
+        if token.kind == Kind::Keyword && token.size == 1 {
+            token.kind = Kind::Invalid;
+        }
+
+        // Symbol -> Float
+        if token.kind == Kind::Symbol && is_float_name(&token.data[.. token.size]) {
+            token.kind = Kind::Float;
+        }
+
         token.finalize();
 
+        // Strings are delimited by quotes which are not part of the token data.
+        let token_end = token_offset + open_size + token.size + close_size;
+
+        let scan_size = position.offset - self.position.offset;
+        let position_at = |size| {
+            debug_assert!(size == scan_size || size + last_size == scan_size);
+            match size < scan_size {
+                true => return prev_position,
+                false => return position
+            }
+        };
+
+        token.span = Span::new(start, position_at(token_end));
+        self.position = position_at(read_size);
+        self.window = &self.window[read_size ..];
+
         return token;
     }
 }
 
-pub fn tokenize(source: &str) -> Vec<Token> {
-    let mut tokens = Vec::new();
-    let mut reader = TokenReader::new(source);
+#[cfg(test)]
+pub fn tokenize<'a>(source: &'a str) -> Vec<Token<'a>> {
+    return TokenReader::new(source, Position::start()).collect();
+}
 
-    loop {
-        let token = reader.next();
+impl <'a> Iterator for TokenReader<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let token = self.read_token();
         match token.kind {
             Kind::Empty => {
-                return tokens;
+                return None;
             }
 
             _ => {
-                tokens.push(token);
+                return Some(token);
             }
         }
     }
 }
 
+fn is_zero(s: &str) -> bool {
+    return s == "0" || s == "-0";
+}
+
+fn is_hex(s: &str) -> bool {
+    return s.starts_with("0x") || s.starts_with("-0x");
+}
+
+fn is_float_name(s: &str) -> bool {
+    return s == "inf" || s == "-inf" || s == "nan";
+}
+
+// Symbols start with a Unicode identifier start character, an underscore or
+// one of the operator characters below, and continue with identifier
+// continue characters (which include digits) or operator characters.
+// Words like nil and true, and symbols like -1 or inf, are told apart
+// from plain symbols by the state machine.
+const SYMBOL_OPERATORS: &str = "*-+/<>=!?%&.$^~_";
+
 fn is_symbol_start(c: char) -> bool {
-    return c.is_ascii_lowercase() || "*-></+!?=".contains(c);
+    return UnicodeXID::is_xid_start(c) || SYMBOL_OPERATORS.contains(c);
 }
 
 fn is_symbol(c: char) -> bool {
-    return is_symbol_start(c) || c.is_numeric();
+    return UnicodeXID::is_xid_continue(c) || SYMBOL_OPERATORS.contains(c);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const NO_SPAN: Span = Span {
+        start:  Position { offset: 0, line: 0, column: 0 },
+        end:    Position { offset: 0, line: 0, column: 0 }
+    };
+
     fn test_tokenize(source: &str, exp_tokens: &Vec<Token>) {
-        let act_tokens = tokenize(source);
+        let act_tokens = tokenize(source)
+            .into_iter()
+            .map(|token| Token { span: NO_SPAN, ..token })
+            .collect::<Vec<_>>();
+
         assert_eq!(exp_tokens, &act_tokens);
     }
 
+    type LineColumn = (usize, usize);
+
+    fn test_spans(source: &str, exp_spans: &Vec<(LineColumn, LineColumn)>) {
+        let act_spans = tokenize(source)
+            .iter()
+            .map(|token| ((token.span.start.line, token.span.start.column),
+                          (token.span.end.line, token.span.end.column)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(exp_spans, &act_spans);
+    }
+
     #[test]
     fn test_empty() {
         let exp_tokens = vec![];
@@ -363,7 +955,7 @@ mod tests {
     #[test]
     fn test_nil() {
         let exp_tokens = vec![
-            Token { kind: Kind::Nil, size: 3, data: "nil" }
+            Token { span: NO_SPAN, kind: Kind::Nil, size: 3, data: "nil" }
         ];
 
         test_tokenize("nil", &exp_tokens);
@@ -373,10 +965,10 @@ mod tests {
     #[test]
     fn test_nil_to_list() {
         let exp_tokens = vec![
-            Token { kind: Kind::Nil,       size: 3, data: "nil" },
-            Token { kind: Kind::ListStart, size: 1, data: "("   },
-            Token { kind: Kind::Nil,       size: 3, data: "nil" },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")"   }
+            Token { span: NO_SPAN, kind: Kind::Nil,       size: 3, data: "nil" },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "("   },
+            Token { span: NO_SPAN, kind: Kind::Nil,       size: 3, data: "nil" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")"   }
         ];
 
         test_tokenize("nil(nil)", &exp_tokens);
@@ -385,7 +977,7 @@ mod tests {
     #[test]
     fn test_nil_invalid() {
         let exp_tokens = vec![
-            Token { kind: Kind::Invalid, size: 4, data: "nil," }
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 4, data: "nil," }
         ];
 
         test_tokenize("nil,", &exp_tokens);
@@ -395,8 +987,8 @@ mod tests {
     #[test]
     fn test_boolean() {
         let exp_tokens = vec![
-            Token { kind: Kind::Boolean, size: 4, data: "true"  },
-            Token { kind: Kind::Boolean, size: 5, data: "false" }
+            Token { span: NO_SPAN, kind: Kind::Boolean, size: 4, data: "true"  },
+            Token { span: NO_SPAN, kind: Kind::Boolean, size: 5, data: "false" }
         ];
 
         test_tokenize("true false", &exp_tokens);
@@ -406,28 +998,28 @@ mod tests {
     #[test]
     fn test_boolean_to_other() {
         let exp_tokens = vec![
-            Token { kind: Kind::Boolean,    size: 4,    data: "true"    },
-            Token { kind: Kind::ListStart,  size: 1,    data: "("       },
-            Token { kind: Kind::Boolean,    size: 4,    data: "true"    },
-            Token { kind: Kind::ListEnd,    size: 1,    data: ")"       },
-            Token { kind: Kind::Boolean,    size: 4,    data: "true"    },
-            Token { kind: Kind::String,     size: 0,    data: ""        },
-            Token { kind: Kind::Boolean,    size: 4,    data: "true"    },
-            Token { kind: Kind::Quote,      size: 1,    data: "'"       },
-            Token { kind: Kind::Symbol,     size: 5,    data: "truee"   },
-            Token { kind: Kind::Invalid,    size: 5,    data: "true$"   }
+            Token { span: NO_SPAN, kind: Kind::Boolean,    size: 4,    data: "true"    },
+            Token { span: NO_SPAN, kind: Kind::ListStart,  size: 1,    data: "("       },
+            Token { span: NO_SPAN, kind: Kind::Boolean,    size: 4,    data: "true"    },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,    size: 1,    data: ")"       },
+            Token { span: NO_SPAN, kind: Kind::Boolean,    size: 4,    data: "true"    },
+            Token { span: NO_SPAN, kind: Kind::String,     size: 0,    data: ""        },
+            Token { span: NO_SPAN, kind: Kind::Boolean,    size: 4,    data: "true"    },
+            Token { span: NO_SPAN, kind: Kind::Quote,      size: 1,    data: "'"       },
+            Token { span: NO_SPAN, kind: Kind::Symbol,     size: 5,    data: "truee"   },
+            Token { span: NO_SPAN, kind: Kind::Invalid,    size: 5,    data: "true@"   }
         ];
 
-        test_tokenize("true( true) true\"\" true' truee true$", &exp_tokens);
+        test_tokenize("true( true) true\"\" true' truee true@", &exp_tokens);
     }
 
     #[test]
     fn test_integer() {
         let exp_tokens = vec![
-            Token { kind: Kind::Integer, size: 1, data: "0"         },
-            Token { kind: Kind::Integer, size: 2, data: "-1"        },
-            Token { kind: Kind::Integer, size: 8, data: "12345678"  },
-            Token { kind: Kind::Integer, size: 9, data: "-12345678" }
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 1, data: "0"         },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 2, data: "-1"        },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 8, data: "12345678"  },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 9, data: "-12345678" }
         ];
 
         test_tokenize("0 -1 12345678 -12345678", &exp_tokens);
@@ -437,10 +1029,10 @@ mod tests {
     #[test]
     fn test_integer_to_list() {
         let exp_tokens = vec![
-            Token { kind: Kind::Integer,   size: 2, data: "-1"       },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")"        },
-            Token { kind: Kind::Integer,   size: 8, data: "12345678" },
-            Token { kind: Kind::ListStart, size: 1, data: "("        }
+            Token { span: NO_SPAN, kind: Kind::Integer,   size: 2, data: "-1"       },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")"        },
+            Token { span: NO_SPAN, kind: Kind::Integer,   size: 8, data: "12345678" },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "("        }
         ];
 
         test_tokenize("-1)12345678(", &exp_tokens);
@@ -449,8 +1041,8 @@ mod tests {
     #[test]
     fn test_integer_invalid() {
         let exp_tokens = vec![
-            Token { kind: Kind::Invalid,   size: 5, data: "-123," },
-            Token { kind: Kind::Invalid,   size: 2, data: "0$"    }
+            Token { span: NO_SPAN, kind: Kind::Invalid,   size: 5, data: "-123," },
+            Token { span: NO_SPAN, kind: Kind::Invalid,   size: 2, data: "0$"    }
         ];
 
         test_tokenize("-123, 0$", &exp_tokens);
@@ -459,9 +1051,9 @@ mod tests {
     #[test]
     fn test_symbol() {
         let exp_tokens = vec![
-            Token { kind: Kind::Symbol, size: 3, data: "foo"  },
-            Token { kind: Kind::Symbol, size: 1, data: "a"    },
-            Token { kind: Kind::Symbol, size: 4, data: "nill" }
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3, data: "foo"  },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 1, data: "a"    },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 4, data: "nill" }
         ];
 
         test_tokenize("foo a nill", &exp_tokens);
@@ -471,10 +1063,10 @@ mod tests {
     #[test]
     fn test_symbol_to_list() {
         let exp_tokens = vec![
-            Token { kind: Kind::Symbol,    size: 3, data: "foo" },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")"   },
-            Token { kind: Kind::Symbol,    size: 3, data: "bar" },
-            Token { kind: Kind::ListStart, size: 1, data: "("   }
+            Token { span: NO_SPAN, kind: Kind::Symbol,    size: 3, data: "foo" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")"   },
+            Token { span: NO_SPAN, kind: Kind::Symbol,    size: 3, data: "bar" },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "("   }
         ];
 
         test_tokenize("foo)bar(", &exp_tokens);
@@ -483,22 +1075,44 @@ mod tests {
     #[test]
     fn test_symbol_invalid() {
         let exp_tokens = vec![
-            Token { kind: Kind::Invalid, size: 5, data: "foo,," },
-            Token { kind: Kind::Invalid, size: 6, data: "äöå"   },
-            Token { kind: Kind::Invalid, size: 4, data: "_123"  }
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 5, data: "foo,," },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 3, data: "a@b"   },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 4, data: "☃x"    },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 2, data: "1a"    }
+        ];
+
+        test_tokenize("foo,, a@b ☃x 1a", &exp_tokens);
+        test_tokenize("\n foo,,\ta@b\n\t☃x\t1a", &exp_tokens);
+    }
+
+    #[test]
+    fn test_symbol_unicode() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 6,  data: "MyType"   },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3,  data: "x_y"      },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 4,  data: "_123"     },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 1,  data: "%"        },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 2,  data: "&&"       },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3,  data: "..."      },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 6,  data: "äöå"      },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 9,  data: "北京市"   },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 4,  data: "λ-1"      },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3,  data: "e\u{301}" },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3,  data: "Nil"      },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 4,  data: "TRUE"     },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 4,  data: "nil?"     }
         ];
 
-        test_tokenize("foo,, äöå _123", &exp_tokens);
-        test_tokenize("\n foo,,\täöå\n\t_123\t", &exp_tokens);
+        test_tokenize("MyType x_y _123 % && ... äöå 北京市 λ-1 e\u{301} Nil TRUE nil?", &exp_tokens);
     }
 
     #[test]
     fn test_string() {
         let exp_tokens = vec![
-            Token { kind: Kind::String, size: 0, data: "",      },
-            Token { kind: Kind::String, size: 3, data: "abc",   },
-            Token { kind: Kind::String, size: 4, data: "a\\nc", },
-            Token { kind: Kind::String, size: 9, data: "北京市" }
+            Token { span: NO_SPAN, kind: Kind::String, size: 0, data: "",      },
+            Token { span: NO_SPAN, kind: Kind::String, size: 3, data: "abc",   },
+            Token { span: NO_SPAN, kind: Kind::String, size: 4, data: "a\\nc", },
+            Token { span: NO_SPAN, kind: Kind::String, size: 9, data: "北京市" }
         ];
 
         test_tokenize("\"\" \"abc\" \"a\\nc\" \"北京市\"", &exp_tokens);
@@ -508,10 +1122,10 @@ mod tests {
     #[test]
     fn test_string_to_list() {
         let exp_tokens = vec![
-            Token { kind: Kind::String,    size: 3, data: "abc",   },
-            Token { kind: Kind::ListStart, size: 1, data: "(",     },
-            Token { kind: Kind::String,    size: 9, data: "北京市" },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")"      }
+            Token { span: NO_SPAN, kind: Kind::String,    size: 3, data: "abc",   },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "(",     },
+            Token { span: NO_SPAN, kind: Kind::String,    size: 9, data: "北京市" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")"      }
         ];
 
         test_tokenize("\"abc\"(\"北京市\")", &exp_tokens);
@@ -520,72 +1134,419 @@ mod tests {
     #[test]
     fn test_string_partial() {
         let exp_tokens = vec![
-            Token { kind: Kind::StringPartial, size: 6, data: "xyz..." }
+            Token { span: NO_SPAN, kind: Kind::StringPartial, size: 6, data: "xyz..." }
         ];
 
         test_tokenize("  \"xyz...", &exp_tokens);
     }
 
+    #[test]
+    fn test_raw_string() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::RawString, size: 0, data: "",            },
+            Token { span: NO_SPAN, kind: Kind::RawString, size: 5, data: r"\d+\n",      },
+            Token { span: NO_SPAN, kind: Kind::RawString, size: 9, data: r##"a "b"#  c"## },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")",           }
+        ];
+
+        test_tokenize(r####"#r"" #r"\d+\n" #r##"a "b"#  c"##)"####, &exp_tokens);
+    }
+
+    #[test]
+    fn test_raw_string_invalid() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Invalid,       size: 3, data: "#r#",  },
+            Token { span: NO_SPAN, kind: Kind::Invalid,       size: 4, data: "#r#x", },
+            Token { span: NO_SPAN, kind: Kind::ListStart,     size: 1, data: "(",    },
+            Token { span: NO_SPAN, kind: Kind::StringPartial, size: 4, data: "a\"#)" }
+        ];
+
+        test_tokenize("#r# #r#x(#r##\"a\"#)", &exp_tokens);
+    }
+
+    #[test]
+    fn test_multi_line_string() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::MultiLineString, size: 0,  data: "",               },
+            Token { span: NO_SPAN, kind: Kind::MultiLineString, size: 11, data: "\n  a \"b\"\n\\\"",     },
+            Token { span: NO_SPAN, kind: Kind::String,          size: 0,  data: "",               },
+            Token { span: NO_SPAN, kind: Kind::StringPartial,   size: 2,  data: "a\"",            }
+        ];
+
+        test_tokenize("\"\"\"\"\"\" \"\"\"\n  a \"b\"\n\\\"\"\"\" \"\" \"\"\"a\"", &exp_tokens);
+    }
+
+    #[test]
+    fn test_span_strings() {
+        let exp_spans = vec![
+            ((1, 1), (1, 10)),
+            ((1, 11), (3, 4)),
+            ((3, 4), (3, 5))
+        ];
+
+        test_spans("#r#\"a\"b\"# \"\"\"\n  x\n\"\"\")", &exp_spans);
+    }
+
     #[test]
     fn test_list() {
         let exp_tokens = vec![
-            Token { kind: Kind::ListStart, size: 1, data: "(" },
-            Token { kind: Kind::ListStart, size: 1, data: "(" },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")" },
-            Token { kind: Kind::ListEnd,   size: 1, data: ")" }
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "(" },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "(" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")" }
         ];
 
         test_tokenize("(())", &exp_tokens);
         test_tokenize("\n(\t (\n  )\n\r\t)\n ", &exp_tokens);
-        //test_tokenize("\n( \t\r\n(   )\t\t )\n", &exp_tokens); // TODO: report \r\n -> \r bug/feature in unicode_segmentation lib
+        test_tokenize("\n( \t\r\n(   )\t\t )\n", &exp_tokens);
     }
 
     #[test]
     fn test_vector() {
         let exp_tokens = vec![
-            Token { kind: Kind::VectorStart, size: 1, data: "[" },
-            Token { kind: Kind::VectorStart, size: 1, data: "[" },
-            Token { kind: Kind::VectorEnd,   size: 1, data: "]" },
-            Token { kind: Kind::VectorEnd,   size: 1, data: "]" }
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1, data: "[" },
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1, data: "[" },
+            Token { span: NO_SPAN, kind: Kind::VectorEnd,   size: 1, data: "]" },
+            Token { span: NO_SPAN, kind: Kind::VectorEnd,   size: 1, data: "]" }
         ];
 
         test_tokenize("[[]]", &exp_tokens);
         test_tokenize("\n[  [\t\r]\n  \t]\n", &exp_tokens);
-        //test_tokenize("\n[ \t\r\n[   ]\t\t ]\n", &exp_tokens); // TODO: report \r\n -> \r bug/feature in unicode_segmentation lib
+        test_tokenize("\n[ \t\r\n[   ]\t\t ]\n", &exp_tokens);
+    }
+
+    #[test]
+    fn test_map() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::MapStart,    size: 1, data: "{"   },
+            Token { span: NO_SPAN, kind: Kind::Symbol,      size: 3, data: "foo" },
+            Token { span: NO_SPAN, kind: Kind::Integer,     size: 1, data: "1"   },
+            Token { span: NO_SPAN, kind: Kind::MapStart,    size: 1, data: "{"   },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   },
+            Token { span: NO_SPAN, kind: Kind::String,      size: 3, data: "bar" },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   }
+        ];
+
+        test_tokenize("{foo 1{}\"bar\"}", &exp_tokens);
+        test_tokenize("\n{ foo\t1 {\n}  \"bar\" }\n", &exp_tokens);
+    }
+
+    #[test]
+    fn test_set() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::SetStart,    size: 2, data: "#{"  },
+            Token { span: NO_SPAN, kind: Kind::Integer,     size: 1, data: "1"   },
+            Token { span: NO_SPAN, kind: Kind::SetStart,    size: 2, data: "#{"  },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   },
+            Token { span: NO_SPAN, kind: Kind::MapStart,    size: 1, data: "{"   },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 1, data: "#"   },
+            Token { span: NO_SPAN, kind: Kind::MapStart,    size: 1, data: "{"   },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"   }
+        ];
+
+        test_tokenize("#{1 #{}{}} # {}", &exp_tokens);
+    }
+
+    #[test]
+    fn test_keyword() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Keyword,     size: 4, data: ":foo"   },
+            Token { span: NO_SPAN, kind: Kind::MapStart,    size: 1, data: "{"      },
+            Token { span: NO_SPAN, kind: Kind::Keyword,     size: 6, data: ":a-b?1" },
+            Token { span: NO_SPAN, kind: Kind::Integer,     size: 1, data: "1"      },
+            Token { span: NO_SPAN, kind: Kind::MapEnd,      size: 1, data: "}"      },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 1, data: ":"      },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 3, data: "::a"    },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 4, data: ":a:b"   },
+            Token { span: NO_SPAN, kind: Kind::Keyword,     size: 4, data: ":nil"   }
+        ];
+
+        test_tokenize(":foo{:a-b?1 1} : ::a :a:b :nil", &exp_tokens);
+    }
+
+    #[test]
+    fn test_char() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 2,  data: "\\a"          },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 6,  data: "\\space"      },
+            Token { span: NO_SPAN, kind: Kind::ListStart,   size: 1,  data: "("            },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 2,  data: "\\("          },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 2,  data: "\\)"          },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,     size: 1,  data: ")"            },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 9,  data: "\\u{1F600}"   },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 4,  data: "\\北"         },
+            Token { span: NO_SPAN, kind: Kind::Char,        size: 2,  data: "\\u"          },
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1,  data: "["            },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 1,  data: "\\"           }
+        ];
+
+        test_tokenize("\\a \\space(\\(\\)) \\u{1F600}\\北 \\u[\\ ", &exp_tokens);
     }
 
     #[test]
     fn test_quote() {
         let exp_tokens = vec![
-            Token { kind: Kind::Quote,  size: 1, data: "'" },
-            Token { kind: Kind::Symbol, size: 3, data: "foo" },
-            Token { kind: Kind::Quote,  size: 1, data: "'" },
-            Token { kind: Kind::Quote,  size: 1, data: "'" },
-            Token { kind: Kind::Symbol, size: 3, data: "bar" },
+            Token { span: NO_SPAN, kind: Kind::Quote,  size: 1, data: "'" },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3, data: "foo" },
+            Token { span: NO_SPAN, kind: Kind::Quote,  size: 1, data: "'" },
+            Token { span: NO_SPAN, kind: Kind::Quote,  size: 1, data: "'" },
+            Token { span: NO_SPAN, kind: Kind::Symbol, size: 3, data: "bar" },
         ];
 
         test_tokenize("'foo '' bar", &exp_tokens);
     }
 
+    #[test]
+    fn test_quasiquote() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Quasiquote,      size: 1, data: "`"   },
+            Token { span: NO_SPAN, kind: Kind::ListStart,       size: 1, data: "("   },
+            Token { span: NO_SPAN, kind: Kind::Symbol,          size: 1, data: "a"   },
+            Token { span: NO_SPAN, kind: Kind::Unquote,         size: 1, data: ","   },
+            Token { span: NO_SPAN, kind: Kind::Symbol,          size: 1, data: "b"   },
+            Token { span: NO_SPAN, kind: Kind::UnquoteSplicing, size: 2, data: ",@"  },
+            Token { span: NO_SPAN, kind: Kind::Symbol,          size: 1, data: "c"   },
+            Token { span: NO_SPAN, kind: Kind::Unquote,         size: 1, data: ","   },
+            Token { span: NO_SPAN, kind: Kind::Quasiquote,      size: 1, data: "`"   },
+            Token { span: NO_SPAN, kind: Kind::Symbol,          size: 1, data: "d"   },
+            Token { span: NO_SPAN, kind: Kind::Quasiquote,      size: 1, data: "`"   },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,         size: 1, data: ")"   },
+            Token { span: NO_SPAN, kind: Kind::Unquote,         size: 1, data: ","   }
+        ];
+
+        test_tokenize("`(a ,b ,@c ,`d`) ,", &exp_tokens);
+    }
+
     #[test]
     fn test_multi() {
         let exp_tokens = vec![
-            Token { kind: Kind::ListStart,   size: 1, data: "("      },
-            Token { kind: Kind::VectorStart, size: 1, data: "["      },
-            Token { kind: Kind::Symbol,      size: 3, data: "foo"    },
-            Token { kind: Kind::Boolean,     size: 4, data: "true"   },
-            Token { kind: Kind::VectorEnd,   size: 1, data: "]"      },
-            Token { kind: Kind::Invalid,     size: 2, data: "a,"     },
-            Token { kind: Kind::VectorStart, size: 1, data: "["      },
-            Token { kind: Kind::Nil,         size: 3, data: "nil"    },
-            Token { kind: Kind::String,      size: 3, data: "abc"    },
-            Token { kind: Kind::Symbol,      size: 3, data: "bar"    },
-            Token { kind: Kind::Boolean,     size: 5, data: "false"  },
-            Token { kind: Kind::String,      size: 9, data: "北京市" },
-            Token { kind: Kind::ListEnd,     size: 1, data: ")"      }
+            Token { span: NO_SPAN, kind: Kind::ListStart,   size: 1, data: "("      },
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1, data: "["      },
+            Token { span: NO_SPAN, kind: Kind::Symbol,      size: 3, data: "foo"    },
+            Token { span: NO_SPAN, kind: Kind::Boolean,     size: 4, data: "true"   },
+            Token { span: NO_SPAN, kind: Kind::VectorEnd,   size: 1, data: "]"      },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 2, data: "a,"     },
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1, data: "["      },
+            Token { span: NO_SPAN, kind: Kind::Nil,         size: 3, data: "nil"    },
+            Token { span: NO_SPAN, kind: Kind::String,      size: 3, data: "abc"    },
+            Token { span: NO_SPAN, kind: Kind::Symbol,      size: 3, data: "bar"    },
+            Token { span: NO_SPAN, kind: Kind::Boolean,     size: 5, data: "false"  },
+            Token { span: NO_SPAN, kind: Kind::String,      size: 9, data: "北京市" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,     size: 1, data: ")"      }
         ];
 
         test_tokenize("([foo true]a,[nil\"abc\"bar false\"北京市\")", &exp_tokens);
         test_tokenize("(\n\t[ foo\ttrue\n] a, [\tnil \"abc\" \nbar false\t\"北京市\" \t\t)\r\n", &exp_tokens);
     }
+
+    #[test]
+    fn test_span_atoms() {
+        let exp_spans = vec![
+            ((1, 1), (1, 4)),
+            ((1, 5), (1, 9)),
+            ((1, 10), (1, 12)),
+            ((1, 13), (1, 16)),
+            ((1, 17), (1, 22))
+        ];
+
+        test_spans("nil true -1 foo \"abc\"", &exp_spans);
+    }
+
+    #[test]
+    fn test_span_lines() {
+        let exp_spans = vec![
+            ((1, 1), (1, 2)),
+            ((1, 2), (1, 5)),
+            ((2, 3), (2, 4)),
+            ((2, 4), (2, 6)),
+            ((2, 6), (2, 7)),
+            ((4, 1), (4, 2))
+        ];
+
+        test_spans("(foo\n  '42)\n\n)", &exp_spans);
+    }
+
+    #[test]
+    fn test_span_unicode() {
+        let exp_spans = vec![
+            ((1, 1), (1, 6)),
+            ((2, 2), (2, 3)),
+            ((2, 3), (2, 7))
+        ];
+
+        test_spans("\"北京市\"\n [\"Åbo", &exp_spans);
+    }
+
+    #[test]
+    fn test_span_offsets() {
+        let tokens = tokenize("  (北京市 \"Åbo\")");
+        let exp_offsets = vec![(2, 3), (3, 12), (13, 19), (19, 20)];
+        let act_offsets = tokens
+            .iter()
+            .map(|token| (token.span.start.offset, token.span.end.offset))
+            .collect::<Vec<_>>();
+
+        assert_eq!(exp_offsets, act_offsets);
+    }
+
+    #[test]
+    fn test_line_comment() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Symbol,      size: 3,  data: "foo"        },
+            Token { span: NO_SPAN, kind: Kind::LineComment, size: 10, data: "; bar (baz" },
+            Token { span: NO_SPAN, kind: Kind::Symbol,      size: 3,  data: "qux"        },
+            Token { span: NO_SPAN, kind: Kind::LineComment, size: 1,  data: ";"          }
+        ];
+
+        test_tokenize("foo ; bar (baz\nqux;", &exp_tokens);
+        test_tokenize("foo; bar (baz\r\n  qux ;", &exp_tokens);
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::BlockComment, size: 9,  data: "#| a\nb |#" },
+            Token { span: NO_SPAN, kind: Kind::Symbol,       size: 3,  data: "foo"        },
+            Token { span: NO_SPAN, kind: Kind::BlockComment, size: 4,  data: "#||#"       }
+        ];
+
+        test_tokenize("#| a\nb |#foo #||#", &exp_tokens);
+    }
+
+    #[test]
+    fn test_block_comment_nested() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::BlockComment, size: 17, data: "#| a #| b |# c |#" },
+            Token { span: NO_SPAN, kind: Kind::Integer,      size: 1,  data: "1"                 }
+        ];
+
+        test_tokenize("#| a #| b |# c |# 1", &exp_tokens);
+    }
+
+    #[test]
+    fn test_block_comment_partial() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::BlockCommentPartial, size: 12, data: "#| a #| b |#" }
+        ];
+
+        test_tokenize("#| a #| b |#", &exp_tokens);
+    }
+
+    #[test]
+    fn test_datum_comment() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::DatumComment, size: 2, data: "#_"  },
+            Token { span: NO_SPAN, kind: Kind::Symbol,       size: 3, data: "foo" },
+            Token { span: NO_SPAN, kind: Kind::DatumComment, size: 2, data: "#_"  },
+            Token { span: NO_SPAN, kind: Kind::ListStart,    size: 1, data: "("   },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,      size: 1, data: ")"   }
+        ];
+
+        test_tokenize("#_foo #_()", &exp_tokens);
+    }
+
+    #[test]
+    fn test_hash_invalid() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Invalid,   size: 2, data: "#1" },
+            Token { span: NO_SPAN, kind: Kind::Invalid,   size: 1, data: "#"  },
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "("  },
+            Token { span: NO_SPAN, kind: Kind::Invalid,   size: 1, data: "#"  }
+        ];
+
+        test_tokenize("#1 #( #", &exp_tokens);
+    }
+
+    #[test]
+    fn test_tag() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Tag,         size: 5, data: "#inst"    },
+            Token { span: NO_SPAN, kind: Kind::String,      size: 1, data: "x"        },
+            Token { span: NO_SPAN, kind: Kind::Tag,         size: 9, data: "#北京/x"  },
+            Token { span: NO_SPAN, kind: Kind::VectorStart, size: 1, data: "["        },
+            Token { span: NO_SPAN, kind: Kind::Tag,         size: 2, data: "#r"       },
+            Token { span: NO_SPAN, kind: Kind::Tag,         size: 4, data: "#rgb"     },
+            Token { span: NO_SPAN, kind: Kind::ListStart,   size: 1, data: "("        },
+            Token { span: NO_SPAN, kind: Kind::Invalid,     size: 5, data: "#ab\\c" },
+            Token { span: NO_SPAN, kind: Kind::Tag,         size: 2, data: "#r"       }
+        ];
+
+        test_tokenize("#inst\"x\" #北京/x[#r #rgb(#ab\\c #r", &exp_tokens);
+    }
+
+    #[test]
+    fn test_string_escaped_quote() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::String, size: 3, data: r#"\"a"#   },
+            Token { span: NO_SPAN, kind: Kind::String, size: 2, data: r#"\\"#    },
+            Token { span: NO_SPAN, kind: Kind::String, size: 5, data: r#"\\\"b"# }
+        ];
+
+        test_tokenize(r#""\"a" "\\""\\\"b""#, &exp_tokens);
+    }
+
+    #[test]
+    fn test_string_escaped_partial() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::StringPartial, size: 5, data: r#"abc\""# }
+        ];
+
+        test_tokenize(r#""abc\""#, &exp_tokens);
+    }
+
+    #[test]
+    fn test_integer_radix() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 4,  data: "0xFF"       },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 7,  data: "-0xbeE1"    },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 5,  data: "0o755"      },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 6,  data: "0b1010"     },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 9,  data: "1_000_000"  },
+            Token { span: NO_SPAN, kind: Kind::Integer, size: 5,  data: "0b102"      },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 4,  data: "0xFG"       },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 3,  data: "1x2"        },
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 4,  data: "0bx1"       }
+        ];
+
+        test_tokenize("0xFF -0xbeE1 0o755 0b1010 1_000_000 0b102 0xFG 1x2 0bx1", &exp_tokens);
+    }
+
+    #[test]
+    fn test_float() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Float, size: 3, data: "1.5"    },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 5, data: "-0.25"  },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 4, data: "1e-9"   },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 6, data: "2.5E+3" },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 3, data: "inf"    },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 4, data: "-inf"   },
+            Token { span: NO_SPAN, kind: Kind::Float, size: 3, data: "nan"    }
+        ];
+
+        test_tokenize("1.5 -0.25 1e-9 2.5E+3 inf -inf nan", &exp_tokens);
+    }
+
+    #[test]
+    fn test_float_to_list() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::ListStart, size: 1, data: "("    },
+            Token { span: NO_SPAN, kind: Kind::Float,     size: 4, data: "-1.0" },
+            Token { span: NO_SPAN, kind: Kind::ListEnd,   size: 1, data: ")"    }
+        ];
+
+        test_tokenize("(-1.0)", &exp_tokens);
+    }
+
+    #[test]
+    fn test_float_invalid() {
+        let exp_tokens = vec![
+            Token { span: NO_SPAN, kind: Kind::Invalid, size: 4, data: "1.5x" },
+            Token { span: NO_SPAN, kind: Kind::Symbol,  size: 4, data: "info" },
+            Token { span: NO_SPAN, kind: Kind::Symbol,  size: 4, data: "-nan" }
+        ];
+
+        test_tokenize("1.5x info -nan", &exp_tokens);
+    }
 }
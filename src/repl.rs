@@ -1,17 +1,19 @@
 extern crate rustyline;
 
-use std::error::Error;
-
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
 use time;
 
+use ::diagnostic::Source;
 use ::eval::env::Env;
 use ::eval::eval::eval;
-use ::read::read;
+use ::read::Form;
+use ::read::stream::Reader;
 use ::util::pretty::pretty;
 
-pub fn enter(mut env: &mut Env) {
+// Warnings are printed before the result of the form that raised them,
+// unless suppressed.
+pub fn enter(env: &mut Env, show_warnings: bool) {
     let history_path = ".solang_history";
 
     let mut rl = Editor::<()>::new();
@@ -20,37 +22,34 @@ pub fn enter(mut env: &mut Env) {
     let version = env!("CARGO_PKG_VERSION");
     println!("solang (Solid Language) {}", version);
 
+    // Everything typed so far, which the spans of read forms point into.
+    let mut source = Source::new("<repl>", "");
+    let mut reader = Reader::new();
     loop {
-        let prompt = format!("{}=> ", env.current_module);
+        // Forms spanning several lines are continued with an aligned prompt.
+        let prompt = match reader.is_incomplete() {
+            false => format!("{}=> ", env.current_module),
+            true => format!("{:width$}.. ", "", width = env.current_module.len())
+        };
+
         let readline  = rl.readline(prompt.as_ref());
         match readline {
             Ok(line) => {
-                match read(&line) {
-                    Ok(sxs) => {
-                        for sx in sxs.iter() {
-                            let t0 = time::precise_time_s();
-                            match eval(&mut env, sx) {
-                                Ok(ref result) => {
-                                    let t1 = time::precise_time_s();
-                                    println!("{}", pretty(result));
-                                    println!("time: {:.6}s", t1 - t0);
-                                },
-
-                                Err(eval_error) => {
-                                    println!("eval error: {}", eval_error.to_string());
-                                }
-                            }
-                        }
+                let chunk = line.clone() + "\n";
+                source.push_str(&chunk);
+                match reader.feed(&chunk, &env.dispatch_table()) {
+                    Ok(forms) => {
+                        eval_forms(env, &forms, &source, true, show_warnings);
                     },
 
                     Err(read_errors) => {
                         for read_error in read_errors {
-                            println!("read error: {}", read_error.to_string());
+                            print!("{}", read_error.diagnostic().render(Some(&source)));
                         }
                     }
                 }
 
-                rl.add_history_entry(line.as_ref());
+                rl.add_history_entry(line.as_str());
             },
 
             Err(ReadlineError::Interrupted) => {
@@ -75,7 +74,36 @@ pub fn enter(mut env: &mut Env) {
 
         Err(err) => {
             println!("Failed to save shell history to {}: {}",
-                     history_path, err.description());
+                     history_path, err);
+        }
+    }
+}
+
+// Evaluates forms read from a stream, printing each result or error
+// along with the source the forms were read from.
+pub fn eval_forms(env: &mut Env, forms: &[Form], source: &Source, show_time: bool, show_warnings: bool) {
+    for form in forms.iter() {
+        let t0 = time::precise_time_s();
+        let result = eval(env, &form.sx);
+
+        for warning in env.take_warnings() {
+            if show_warnings {
+                print!("{}", warning.diagnostic(Some(form)).render(Some(source)));
+            }
+        }
+
+        match result {
+            Ok(ref result) => {
+                let t1 = time::precise_time_s();
+                println!("{}", pretty(result));
+                if show_time {
+                    println!("time: {:.6}s", t1 - t0);
+                }
+            },
+
+            Err(eval_error) => {
+                print!("{}", eval_error.diagnostic(Some(form)).render(Some(source)));
+            }
         }
     }
 }
@@ -1,11 +1,14 @@
+use std::io::{self, BufRead};
 use std::path::Path;
 use std::sync::Arc;
 
+use ::diagnostic::Source;
 use ::eval::env::Env;
 use ::eval::module;
+use ::read::stream::Reader;
 use ::repl;
 
-pub fn run(path: &str, interactive: bool) {
+pub fn run(path: &str, interactive: bool, show_warnings: bool) {
     let module_name = module::from_filename(path);
 
     let module_path = Path::new(path).parent().expect("failed to read module path parent");
@@ -18,11 +21,61 @@ pub fn run(path: &str, interactive: bool) {
 
     match module::load_use(&mut env, &module_name) {
         Ok(_) => (),
-        Err(eval_error) => println!("failed to run file {}: {}", path, eval_error.to_string())
+        Err(eval_error) => print!("{}", eval_error.diagnostic(None).render(None))
+    }
+
+    for warning in env.take_warnings() {
+        if show_warnings {
+            print!("{}", warning.diagnostic(None).render(None));
+        }
     }
 
     if interactive {
         env.current_module = module_name;
-        repl::enter(&mut env);
+        repl::enter(&mut env, show_warnings);
+    }
+}
+
+// Evaluates forms piped through standard input as soon as they are read,
+// printing their results.
+pub fn run_stdin(show_warnings: bool) {
+    let module_paths = vec![
+        "./".to_string()
+    ];
+
+    let current_module = sx_symbol_unwrapped!("stdin");
+    let mut env = Env::new(&module_paths, &current_module);
+
+    let stdin = io::stdin();
+    let mut source = Source::new("<stdin>", "");
+    let mut reader = Reader::new();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                println!("failed to read standard input: {}", err);
+                return;
+            }
+        };
+
+        let chunk = line + "\n";
+        source.push_str(&chunk);
+        match reader.feed(&chunk, &env.dispatch_table()) {
+            Ok(forms) => repl::eval_forms(&mut env, &forms, &source, false, show_warnings),
+            Err(read_errors) => {
+                for read_error in read_errors {
+                    print!("{}", read_error.diagnostic().render(Some(&source)));
+                }
+            }
+        }
+    }
+
+    match reader.finish(&env.dispatch_table()) {
+        Ok(forms) => repl::eval_forms(&mut env, &forms, &source, false, show_warnings),
+        Err(read_errors) => {
+            for read_error in read_errors {
+                print!("{}", read_error.diagnostic().render(Some(&source)));
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,22 +1,34 @@
 use std::fmt;
 use std::clone::Clone;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::string::ToString;
 use std::sync::Arc;
 
 use im;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
 use ::eval;
 use ::eval::env::Env;
+use ::eval::scope::Scope;
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum Sx {
     Nil,
     Boolean(SxBoolean),
     Integer(SxInteger),
+    BigInteger(SxBigInteger),
+    Float(SxFloat),
     Symbol(SxSymbol),
+    Keyword(SxKeyword),
+    Char(SxChar),
     String(SxString),
     List(SxList),
     Vector(SxVector),
+    Map(SxMap),
+    Set(SxSet),
+    Tagged(SxTagged),
     Quote(SxQuote),
     Builtin(SxBuiltin),
     Function(SxFunction)
@@ -24,34 +36,95 @@ pub enum Sx {
 
 pub type SxBoolean      = bool;
 pub type SxInteger      = i64;
+pub type SxBigInteger   = Arc<BigInt>;
 pub type SxString       = Arc<String>;
 pub type SxSymbol       = Arc<String>;
+pub type SxKeyword      = Arc<String>;
+pub type SxChar         = Arc<String>;
 pub type SxList         = Arc<Vec<Sx>>;
 pub type SxVector       = Arc<im::Vector<Sx>>;
+pub type SxMap          = Arc<im::OrdMap<Sx, Sx>>;
+pub type SxTagged       = Arc<SxTaggedInfo>;
 pub type SxQuote        = Arc<Sx>;
 pub type SxBuiltin      = &'static SxBuiltinInfo;
 pub type SxFunction     = Arc<SxFunctionInfo>;
 
+// Floats compare and hash by value, except that all NaNs are equal to each
+// other and negative zero is equal to zero, so that equality stays an
+// equivalence relation and Sx can derive Eq. Ordering follows the IEEE 754
+// total order, which places NaN after all other floats.
+#[derive(Clone, Copy, Debug)]
+pub struct SxFloat(pub f64);
+
+// Sets compare, order and hash by their sorted elements, since the iteration
+// order of a hash set depends on its hasher and insertion history.
+#[derive(Clone, Debug)]
+pub struct SxSet(pub Arc<im::HashSet<Sx>>);
+
+// Parameters beyond the minimum arity are optional, and the last parameter
+// takes the remaining arguments when there is no maximum arity.
 pub struct SxBuiltinInfo {
     pub name:       &'static str,
     pub min_arity:  usize,
     pub max_arity:  Option<usize>,
+    pub params:     &'static [SxParam],
     pub callback:   SxBuiltinCallback
 }
 
+pub struct SxParam {
+    pub name:   &'static str,
+    pub ty:     SxType
+}
+
+// The values a builtin parameter accepts. Primitive arguments are checked
+// against their parameter after evaluation, special forms check their own.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum SxType {
+    Any,
+    Integer,
+    Number,
+    Symbol,
+    String,
+    Char,
+    List,
+    Map,
+    Set,
+    Tagged,
+    Function,
+    Sequence,
+    NonEmptyList,
+    ScalarValue,
+    ScalarChar,
+    CharSequence
+}
+
+// Tail specials return a form to evaluate in their place, such as the taken
+// branch of an if, so that calls in tail position run in constant stack.
 pub enum SxBuiltinCallback {
     Special(SxBuiltinFn),
+    TailSpecial(SxTailFn),
     Primitive(SxBuiltinFn)
 }
 
 pub type SxBuiltinFn = fn(&mut Env, &[Sx]) -> eval::Result;
+pub type SxTailFn = fn(&mut Env, &[Sx]) -> eval::TailResult;
+
+// A value read from a tagged literal such as #inst "2018-11-05T13:15:30Z",
+// which prints back as the tag followed by the value.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SxTaggedInfo {
+    pub tag:    SxSymbol,
+    pub value:  Sx
+}
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct SxFunctionInfo {
     pub module:     SxSymbol,
     pub arity:      usize,
     pub bindings:   Vec<SxSymbol>,
-    pub body:       SxList
+    pub body:       SxList,
+    // The local bindings in effect where the function was created.
+    pub scope:      Scope
 }
 
 #[macro_export]
@@ -69,6 +142,11 @@ macro_rules! sx_integer {
     ($e:expr) => (Sx::Integer($e));
 }
 
+#[macro_export]
+macro_rules! sx_float {
+    ($e:expr) => (Sx::Float(SxFloat($e)));
+}
+
 #[macro_export]
 macro_rules! sx_symbol {
     ($e:expr) => (Sx::Symbol(Arc::new($e.to_string())));
@@ -79,6 +157,16 @@ macro_rules! sx_symbol_unwrapped {
     ($e:expr) => (Arc::new($e.to_string()));
 }
 
+#[macro_export]
+macro_rules! sx_keyword {
+    ($e:expr) => (Sx::Keyword(Arc::new($e.to_string())));
+}
+
+#[macro_export]
+macro_rules! sx_char {
+    ($e:expr) => (Sx::Char(Arc::new($e.to_string())));
+}
+
 #[macro_export]
 macro_rules! sx_string {
     ($e:expr) => (Sx::String(Arc::new($e.to_string())));
@@ -104,6 +192,26 @@ macro_rules! sx_vector_from_vec {
     ($e:expr) => (Sx::Vector(Arc::new(im::Vector::from($e))));
 }
 
+#[macro_export]
+macro_rules! sx_map {
+    [ $( $k:expr => $v:expr ),*] => (Sx::Map(Arc::new(ordmap!{$($k => $v),*})));
+}
+
+#[macro_export]
+macro_rules! sx_set {
+    [ $( $e:expr ),*] => (Sx::Set(SxSet(Arc::new(hashset![$($e),*]))));
+}
+
+#[macro_export]
+macro_rules! sx_set_from_vec {
+    ($e:expr) => (Sx::Set(SxSet(Arc::new(im::HashSet::from($e)))));
+}
+
+#[macro_export]
+macro_rules! sx_tagged {
+    ($tag:expr, $value:expr) => (Sx::Tagged(Arc::new(SxTaggedInfo { tag: Arc::new($tag.to_string()), value: $value })));
+}
+
 #[macro_export]
 macro_rules! sx_quote {
     ($e:expr) => (Sx::Quote(Arc::new($e)));
@@ -118,9 +226,17 @@ impl ToString for Sx {
 
             Sx::Integer(i) => format!("{}", i),
 
+            Sx::BigInteger(i) => format!("{}", i),
+
+            Sx::Float(f) => f.to_string(),
+
             Sx::Symbol(s) => format!("{}", s),
 
-            Sx::String(s) => format!("\"{}\"", s),
+            Sx::Keyword(k) => format!(":{}", k),
+
+            Sx::Char(c) => format!("\\{}", char_name(c)),
+
+            Sx::String(s) => format!("\"{}\"", escape_string(s)),
 
             Sx::List(sxs) => {
                 let mut s = String::new();
@@ -160,6 +276,46 @@ impl ToString for Sx {
                 return s;
             }
 
+            Sx::Map(m) => {
+                let mut s = String::new();
+                let mut first = true;
+
+                s.push('{');
+                for (key, value) in m.iter() {
+                    if !first {
+                        s.push(' ');
+                    }
+
+                    first = false;
+                    s.push_str(key.to_string().as_ref());
+                    s.push(' ');
+                    s.push_str(value.to_string().as_ref());
+                }
+                s.push('}');
+
+                return s;
+            },
+
+            Sx::Set(set) => {
+                let mut s = String::new();
+                let mut first = true;
+
+                s.push_str("#{");
+                for sx in set.sorted().iter() {
+                    if !first {
+                        s.push(' ');
+                    }
+
+                    first = false;
+                    s.push_str(sx.to_string().as_ref());
+                }
+                s.push('}');
+
+                return s;
+            },
+
+            Sx::Tagged(t) => format!("#{} {}", t.tag, t.value.to_string()),
+
             Sx::Quote(sx) => format!("'{}", sx.to_string()),
 
             Sx::Builtin(b) => b.to_string(),
@@ -169,6 +325,165 @@ impl ToString for Sx {
     }
 }
 
+impl Sx {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Sx::Nil             => return "nil",
+            Sx::Boolean(_)      => return "boolean",
+            Sx::Integer(_)      |
+            Sx::BigInteger(_)   => return "integer",
+            Sx::Float(_)        => return "float",
+            Sx::Symbol(_)       => return "symbol",
+            Sx::Keyword(_)      => return "keyword",
+            Sx::Char(_)         => return "char",
+            Sx::String(_)       => return "string",
+            Sx::List(_)         => return "list",
+            Sx::Vector(_)       => return "vector",
+            Sx::Map(_)          => return "map",
+            Sx::Set(_)          => return "set",
+            Sx::Tagged(_)       => return "tagged value",
+            Sx::Quote(_)        => return "quote",
+            Sx::Builtin(_)      => return "builtin",
+            Sx::Function(_)     => return "function"
+        }
+    }
+
+    // Integers are kept as Sx::Integer whenever they fit in one, so that
+    // equal integers always have the same representation.
+    pub fn from_big_integer(i: BigInt) -> Sx {
+        match i.to_i64() {
+            Some(small) => return sx_integer!(small),
+            None        => return Sx::BigInteger(Arc::new(i))
+        }
+    }
+}
+
+// Names a character, which may be a grapheme cluster of several code points,
+// so that reading it back after a backslash yields the original character.
+fn char_name(c: &str) -> String {
+    match c {
+        " "     => return "space".to_string(),
+        "\n"    => return "newline".to_string(),
+        "\t"    => return "tab".to_string(),
+        "\r"    => return "return".to_string(),
+        _       => {
+            match (c.chars().next(), c.chars().count()) {
+                (Some(only), 1) if only.is_control() => return format!("u{{{:x}}}", only as u32),
+                _ => return c.to_string()
+            }
+        }
+    }
+}
+
+// Escapes a string so that reading it back between double quotes yields
+// the original string.
+fn escape_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"'     => escaped.push_str("\\\""),
+            '\\'    => escaped.push_str("\\\\"),
+            '\n'    => escaped.push_str("\\n"),
+            '\t'    => escaped.push_str("\\t"),
+            '\r'    => escaped.push_str("\\r"),
+            '\0'    => escaped.push_str("\\0"),
+            _ if c.is_control() => escaped.push_str(format!("\\u{{{:x}}}", c as u32).as_ref()),
+            _       => escaped.push(c)
+        }
+    }
+
+    return escaped;
+}
+
+impl SxFloat {
+    fn canonical(&self) -> f64 {
+        if self.0.is_nan() {
+            return f64::NAN;
+        }
+
+        if self.0 == 0.0 {
+            return 0.0;
+        }
+
+        return self.0;
+    }
+
+    fn bits(&self) -> u64 {
+        return self.canonical().to_bits();
+    }
+}
+
+impl Eq for SxFloat {}
+
+impl PartialEq for SxFloat {
+    fn eq(&self, other: &SxFloat) -> bool {
+        return self.bits() == other.bits();
+    }
+}
+
+impl Ord for SxFloat {
+    fn cmp(&self, other: &SxFloat) -> Ordering {
+        return self.canonical().total_cmp(&other.canonical());
+    }
+}
+
+impl PartialOrd for SxFloat {
+    fn partial_cmp(&self, other: &SxFloat) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Hash for SxFloat {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bits().hash(state);
+    }
+}
+
+// Prints floats so that they read back as floats, e.g. 1.0 instead of 1.
+impl ToString for SxFloat {
+    fn to_string(&self) -> String {
+        if self.0.is_nan() {
+            return "nan".to_string();
+        }
+
+        return format!("{:?}", self.0);
+    }
+}
+
+impl SxSet {
+    pub fn sorted(&self) -> Vec<&Sx> {
+        let mut sxs = self.0.iter().collect::<Vec<_>>();
+        sxs.sort();
+        return sxs;
+    }
+}
+
+impl Eq for SxSet {}
+
+impl PartialEq for SxSet {
+    fn eq(&self, other: &SxSet) -> bool {
+        return self.0 == other.0;
+    }
+}
+
+impl Ord for SxSet {
+    fn cmp(&self, other: &SxSet) -> Ordering {
+        return self.sorted().cmp(&other.sorted());
+    }
+}
+
+impl PartialOrd for SxSet {
+    fn partial_cmp(&self, other: &SxSet) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
+
+impl Hash for SxSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted().hash(state);
+    }
+}
+
 impl Copy for SxBuiltinCallback {}
 
 impl Clone for SxBuiltinCallback {
@@ -187,21 +502,122 @@ impl Eq for SxBuiltinInfo {}
 
 impl PartialEq for SxBuiltinInfo {
     fn eq(&self, other: &SxBuiltinInfo) -> bool {
-        let info_eq = self.name == other.name
-            && self.min_arity == other.min_arity
-            && self.max_arity == other.max_arity;
+        return self.key() == other.key();
+    }
+}
 
-        let callback_eq = match (self.callback, other.callback) {
-            (SxBuiltinCallback::Special(_), SxBuiltinCallback::Primitive(_)) => false,
+impl SxBuiltinInfo {
+    fn key(&self) -> (&'static str, usize, Option<usize>, u8, usize) {
+        match self.callback {
+            SxBuiltinCallback::Special(f) => (self.name, self.min_arity, self.max_arity, 0, f as usize),
+            SxBuiltinCallback::TailSpecial(f) => (self.name, self.min_arity, self.max_arity, 1, f as usize),
+            SxBuiltinCallback::Primitive(f) => (self.name, self.min_arity, self.max_arity, 2, f as usize)
+        }
+    }
+}
 
-            (SxBuiltinCallback::Primitive(_), SxBuiltinCallback::Special(_)) => false,
+// Builtins are ordered by name and arity, and then by callback address,
+// which is only meaningful within a single run.
+impl Ord for SxBuiltinInfo {
+    fn cmp(&self, other: &SxBuiltinInfo) -> Ordering {
+        return self.key().cmp(&other.key());
+    }
+}
 
-            (SxBuiltinCallback::Special(a), SxBuiltinCallback::Special(b)) => a as usize == b as usize,
+impl PartialOrd for SxBuiltinInfo {
+    fn partial_cmp(&self, other: &SxBuiltinInfo) -> Option<Ordering> {
+        return Some(self.cmp(other));
+    }
+}
 
-            (SxBuiltinCallback::Primitive(a), SxBuiltinCallback::Primitive(b)) => a as usize == b as usize
-        };
+impl Hash for SxBuiltinInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl SxBuiltinInfo {
+    // The parameter of the argument at the given index, which is the rest
+    // parameter for arguments past the last one, if any.
+    pub fn param(&self, index: usize) -> Option<&'static SxParam> {
+        let params = self.params;
+        match params.get(index) {
+            Some(param) => return Some(param),
+            None if self.max_arity.is_none() => return params.last(),
+            None => return None
+        }
+    }
+
+    // Describes the call of a builtin with optional parameters in brackets
+    // and the parameter of the remaining arguments after an ampersand,
+    // e.g. (get map key [default]) or (- x & ys).
+    pub fn signature(&self) -> String {
+        let mut s = format!("({}", self.name);
+        for (index, param) in self.params.iter().enumerate() {
+            if index + 1 == self.params.len() && self.max_arity.is_none() {
+                s += &format!(" & {}", param.name);
+            } else if index >= self.min_arity {
+                s += &format!(" [{}]", param.name);
+            } else {
+                s += &format!(" {}", param.name);
+            }
+        }
 
-        return info_eq && callback_eq;
+        return s + ")";
+    }
+}
+
+impl SxType {
+    pub fn accepts(&self, sx: &Sx) -> bool {
+        match (self, sx) {
+            (SxType::Any, _)                                    => return true,
+            (SxType::Integer, Sx::Integer(_))                   |
+            (SxType::Integer, Sx::BigInteger(_))                |
+            (SxType::Number, Sx::Integer(_))                    |
+            (SxType::Number, Sx::BigInteger(_))                 |
+            (SxType::Number, Sx::Float(_))                      |
+            (SxType::Symbol, Sx::Symbol(_))                     |
+            (SxType::String, Sx::String(_))                     |
+            (SxType::Char, Sx::Char(_))                         |
+            (SxType::List, Sx::List(_))                         |
+            (SxType::Map, Sx::Map(_))                           |
+            (SxType::Set, Sx::Set(_))                           |
+            (SxType::Tagged, Sx::Tagged(_))                     |
+            (SxType::Function, Sx::Builtin(_))                  |
+            (SxType::Function, Sx::Function(_))                 |
+            (SxType::Sequence, Sx::List(_))                     |
+            (SxType::Sequence, Sx::Vector(_))                   => return true,
+            (SxType::NonEmptyList, Sx::List(sxs))               => return !sxs.is_empty(),
+            (SxType::ScalarValue, Sx::Integer(i))               => {
+                return 0 <= *i && *i <= u32::MAX as i64 && ::std::char::from_u32(*i as u32).is_some();
+            },
+            (SxType::ScalarChar, Sx::Char(c))                   => return c.chars().count() == 1,
+            (SxType::CharSequence, Sx::List(sxs))               => return sxs.iter().all(|sx| SxType::Char.accepts(sx)),
+            (SxType::CharSequence, Sx::Vector(sxs))             => return sxs.iter().all(|sx| SxType::Char.accepts(sx)),
+            _                                                   => return false
+        }
+    }
+
+    // Names the type with an article, e.g. "a number".
+    pub fn description(&self) -> &'static str {
+        match self {
+            SxType::Any             => return "any value",
+            SxType::Integer         => return "an integer",
+            SxType::Number          => return "a number",
+            SxType::Symbol          => return "a symbol",
+            SxType::String          => return "a string",
+            SxType::Char            => return "a char",
+            SxType::List            => return "a list",
+            SxType::Map             => return "a map",
+            SxType::Set             => return "a set",
+            SxType::Tagged          => return "a tagged value",
+            SxType::Function        => return "a function",
+            SxType::Sequence        => return "a list or vector",
+            SxType::NonEmptyList    => return "a non-empty list",
+            SxType::ScalarValue     => return "a Unicode scalar value",
+            SxType::ScalarChar      => return "a char of one code point",
+            SxType::CharSequence    => return "a list or vector of chars"
+        }
     }
 }
 
@@ -216,7 +632,7 @@ impl ToString for SxBuiltinInfo {
         let info_str = format!("name: {}, arity: {}", self.name, arity_str);
 
         match self.callback {
-            SxBuiltinCallback::Special(_) => {
+            SxBuiltinCallback::Special(_) | SxBuiltinCallback::TailSpecial(_) => {
                 return format!("#special<{}>", info_str);
             },
 
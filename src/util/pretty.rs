@@ -66,6 +66,60 @@ fn do_pretty(sx: &Sx, buf: &mut String, offset: usize) {
             buf.write_char(']').expect("write failed");
         },
 
+        Sx::Set(set) => {
+            let mut i = 0;
+            let sxs = set.sorted();
+            let len = sxs.len();
+            indent("#{", buf, offset);
+            for sub_sx in sxs.iter() {
+                match i {
+                    0 => {
+                        do_pretty(sub_sx, buf, 0);
+                    },
+
+                    _ => {
+                        do_pretty(sub_sx, buf, offset + 2);
+                    }
+                }
+
+                if i < len - 1 {
+                    buf.write_char('\n').expect("write failed");
+                }
+
+                i += 1;
+            }
+
+            buf.write_char('}').expect("write failed");
+        },
+
+        Sx::Map(m) => {
+            let mut i = 0;
+            let len = m.len();
+            indent("{", buf, offset);
+            for (key, value) in m.iter() {
+                match i {
+                    0 => {
+                        do_pretty(key, buf, 0);
+                    },
+
+                    _ => {
+                        do_pretty(key, buf, offset + 1);
+                    }
+                }
+
+                buf.write_char(' ').expect("write failed");
+                do_pretty(value, buf, 0);
+
+                if i < len - 1 {
+                    buf.write_char('\n').expect("write failed");
+                }
+
+                i += 1;
+            }
+
+            buf.write_char('}').expect("write failed");
+        },
+
         _ => indent(s.as_ref(), buf, offset)
     }
 }